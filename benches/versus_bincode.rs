@@ -2,8 +2,8 @@ use serde::{Deserialize, Serialize};
 
 use criterion::{black_box, criterion_group, criterion_main, Benchmark, Criterion};
 
-use bincode::{deserialize_in_place, serialize_into};
-use peek_poke::{PeekPoke, Poke};
+use bincode::{serialize_into, DefaultOptions, Deserializer, Options};
+use peek_poke::{PeekPoke, PeekReader, Poke};
 use std::{io, ptr};
 
 #[derive(Debug, Default, Deserialize, PartialEq, PeekPoke, Serialize)]
@@ -29,6 +29,7 @@ pub type PipelineSourceId = u32;
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, PeekPoke, Serialize)]
 pub struct PipelineId(pub PipelineSourceId, pub u32);
 
+#[allow(clippy::derivable_impls)]
 impl Default for PipelineId {
     fn default() -> Self {
         PipelineId(0, 0)
@@ -70,6 +71,24 @@ pub struct CommonItemProperties {
     pub is_backface_visible: bool,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, PeekPoke, Serialize)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    pub color: u32,
+}
+
+fn vertices() -> Vec<Vertex> {
+    (0..1024)
+        .map(|i| Vertex {
+            position: [i as f32, 2.0, 3.0],
+            uv: [0.5, 1.0],
+            color: i,
+        })
+        .collect()
+}
+
 // This is used by webrender_api
 #[derive(Clone, Copy)]
 struct UnsafeReader {
@@ -187,12 +206,88 @@ fn criterion_benchmark(c: &mut Criterion) {
             let mut result = CommonItemProperties::default();
             let reader = UnsafeReader::new(&bytes);
             b.iter(|| {
-                let reader = bincode::IoReader::new(reader);
-                black_box(deserialize_in_place(reader, &mut result));
+                let options = DefaultOptions::new()
+                    .with_fixint_encoding()
+                    .allow_trailing_bytes();
+                let mut deserializer = Deserializer::with_reader(reader, options);
+                black_box(Deserialize::deserialize_in_place(
+                    &mut deserializer,
+                    &mut result,
+                ));
+            })
+        }),
+    );
+}
+
+#[allow(unused_must_use)]
+fn slice_benchmark(c: &mut Criterion) {
+    c.bench(
+        "slice::serialize",
+        Benchmark::new("peek_poke::poke_slice_into_vec", |b| {
+            let vertices = vertices();
+            let mut buffer = Vec::with_capacity(64 * 1024);
+            b.iter(|| {
+                buffer.clear();
+                peek_poke::poke_slice_into_vec(black_box(&vertices), &mut buffer);
+            })
+        })
+        .with_function("peek_poke::poke_extend_vec", |b| {
+            let vertices = vertices();
+            let mut buffer = Vec::with_capacity(64 * 1024);
+            b.iter(|| {
+                buffer.clear();
+                peek_poke::poke_extend_vec(black_box(&vertices).iter(), &mut buffer);
+            })
+        })
+        .with_function("bincode::serialize", |b| {
+            let vertices = vertices();
+            let mut buffer = Vec::with_capacity(64 * 1024);
+            b.iter(|| {
+                buffer.clear();
+                black_box(serialize_into(&mut buffer, black_box(&vertices)));
+            })
+        }),
+    );
+
+    c.bench(
+        "slice::deserialize",
+        Benchmark::new("peek_poke::read_slice_into", |b| {
+            let mut bytes = Vec::new();
+            peek_poke::poke_slice_into_vec(&vertices(), &mut bytes);
+            let mut result = vec![Vertex::default(); 1024];
+            b.iter(|| {
+                black_box(PeekReader::new(black_box(&bytes)).read_slice_into(&mut result));
+            })
+        })
+        .with_function("peek_poke::read_into", |b| {
+            let mut bytes = Vec::new();
+            peek_poke::poke_slice_into_vec(&vertices(), &mut bytes);
+            let mut result = vec![Vertex::default(); 1024];
+            b.iter(|| {
+                let mut reader = PeekReader::new(black_box(&bytes));
+                for v in &mut result {
+                    black_box(reader.read_into(v));
+                }
+            })
+        })
+        .with_function("bincode::deserialize", |b| {
+            let mut bytes = Vec::new();
+            serialize_into(&mut bytes, &vertices()).unwrap();
+            let mut result = Vec::<Vertex>::new();
+            let reader = UnsafeReader::new(&bytes);
+            b.iter(|| {
+                let options = DefaultOptions::new()
+                    .with_fixint_encoding()
+                    .allow_trailing_bytes();
+                let mut deserializer = Deserializer::with_reader(reader, options);
+                black_box(Deserialize::deserialize_in_place(
+                    &mut deserializer,
+                    &mut result,
+                ));
             })
         }),
     );
 }
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(benches, criterion_benchmark, slice_benchmark);
 criterion_main!(benches);
@@ -1,4 +1,4 @@
-use peek_poke::{Peek, PeekPoke, Poke};
+use peek_poke::{PeekPoke, PeekReader, Poke};
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, PeekPoke)]
@@ -26,14 +26,8 @@ pub type PipelineSourceId = u32;
 #[derive(Clone, Copy, Debug, PartialEq, PeekPoke)]
 pub struct PipelineId(pub PipelineSourceId, pub u32);
 
-impl Default for PipelineId {
-    fn default() -> Self {
-        PipelineId(0, 0)
-    }
-}
-
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+#[derive(Clone, Copy, Debug, PartialEq, PeekPoke)]
 pub struct ClipChainId(pub u64, pub PipelineId);
 
 #[repr(C)]
@@ -43,12 +37,6 @@ pub enum ClipId {
     ClipChain(ClipChainId),
 }
 
-impl Default for ClipId {
-    fn default() -> Self {
-        ClipId::Clip(!0, PipelineId::default())
-    }
-}
-
 pub type ItemTag = (u64, u16);
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, PeekPoke)]
@@ -79,15 +67,6 @@ fn poke_into<T: Poke>(bytes: &mut Vec<u8>, x: &T) {
     }
 }
 
-fn peek_from<T: Copy + Peek>(bytes: &[u8]) -> T {
-    assert!(bytes.len() >= <T>::MAX_SIZE);
-    let ptr = bytes.as_ptr();
-    let (result, new_ptr): (T, _) = unsafe { peek_poke::peek_from_uninit(bytes.as_ptr()) };
-    let size = (new_ptr as usize) - (ptr as usize);
-    assert!(size <= bytes.len());
-    result
-}
-
 pub fn main() {
     let x = CommonItemProperties {
         clip_rect: Rect {
@@ -110,7 +89,9 @@ pub fn main() {
             0, 0, 0, 0, 7, 0, 1
         ]
     );
-    let y: CommonItemProperties = peek_from(&bytes);
+    let mut reader = PeekReader::new(&bytes);
+    let y: CommonItemProperties = reader.read().unwrap();
+    assert!(reader.is_empty());
     println!("{:?}", y);
     assert_eq!(x, y);
 }
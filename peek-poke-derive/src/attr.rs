@@ -0,0 +1,393 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing of `#[peek_poke(...)]` attributes.
+
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Attribute, Error, Expr, ExprLit, ExprPath, Ident, Lit, LitInt, Meta, NestedMeta, Path, Result,
+    Token, Type,
+};
+use synstructure::Structure;
+
+/// The integer types that can be used for enum tags.
+const TAG_TYPES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize",
+];
+
+/// The integer types that `Fixed` can be serialized as.
+const FIXED_TYPES: &[&str] = &["u8", "u16", "u32", "i8", "i16", "i32"];
+
+/// One `name` or `name = value` item of a `#[peek_poke(...)]` attribute.
+///
+/// `Meta` only allows literals as values, which rules out `tag = u16`.
+struct Item {
+    name: Ident,
+    value: Option<Expr>,
+}
+
+impl Parse for Item {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name = input.parse()?;
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        Ok(Item { name, value })
+    }
+}
+
+impl Item {
+    fn unknown(&self) -> Error {
+        Error::new(self.name.span(), "unknown `peek_poke` attribute")
+    }
+
+    /// Checks that the item is a bare `name`.
+    fn flag(&self) -> Result<()> {
+        match self.value {
+            None => Ok(()),
+            Some(ref value) => Err(Error::new_spanned(
+                value,
+                format!("`{}` doesn't take a value", self.name),
+            )),
+        }
+    }
+
+    /// The value of a `name = value` item.
+    fn value(&self) -> Result<&Expr> {
+        self.value
+            .as_ref()
+            .ok_or_else(|| Error::new(self.name.span(), format!("expected `{} = ...`", self.name)))
+    }
+
+    fn lit_int(&self) -> Result<LitInt> {
+        match *self.value()? {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(ref lit),
+                ..
+            }) => Ok(lit.clone()),
+            ref value => Err(Error::new_spanned(value, "expected an integer literal")),
+        }
+    }
+
+    /// The path in a `name = "path"` item.
+    fn path(&self) -> Result<Path> {
+        match *self.value()? {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(ref lit),
+                ..
+            }) => lit.parse(),
+            ref value => Err(Error::new_spanned(value, "expected a string literal")),
+        }
+    }
+
+    fn ident(&self) -> Result<Ident> {
+        match *self.value()? {
+            Expr::Path(ExprPath { ref path, .. }) if path.get_ident().is_some() => {
+                Ok(path.get_ident().unwrap().clone())
+            }
+            ref value => Err(Error::new_spanned(value, "expected an identifier")),
+        }
+    }
+}
+
+/// Collects the items of all `#[peek_poke(...)]` attributes in `attrs`.
+fn peek_poke_items(attrs: &[Attribute]) -> Result<Vec<Item>> {
+    let mut items = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path.is_ident("peek_poke")) {
+        items.extend(attr.parse_args_with(Punctuated::<Item, Token![,]>::parse_terminated)?);
+    }
+    Ok(items)
+}
+
+/// The integer type in `#[repr(...)]`, if any.
+fn repr_int(attrs: &[Attribute]) -> Result<Option<Ident>> {
+    for attr in attrs.iter().filter(|a| a.path.is_ident("repr")) {
+        if let Meta::List(list) = attr.parse_meta()? {
+            for item in list.nested {
+                if let NestedMeta::Meta(Meta::Path(ref path)) = item {
+                    match path.get_ident() {
+                        Some(ident) if TAG_TYPES.contains(&&*ident.to_string()) => {
+                            return Ok(Some(ident.clone()))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Whether `#[repr(C)]` or `#[repr(transparent)]` fixes the order of fields
+/// in memory.
+fn repr_c(attrs: &[Attribute]) -> Result<bool> {
+    for attr in attrs.iter().filter(|a| a.path.is_ident("repr")) {
+        if let Meta::List(list) = attr.parse_meta()? {
+            for item in list.nested {
+                if let NestedMeta::Meta(Meta::Path(ref path)) = item {
+                    if path.is_ident("C") || path.is_ident("transparent") {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Attributes on the struct or enum being derived.
+#[derive(Default)]
+pub struct ContainerAttrs {
+    /// `#[peek_poke(max_size = N)]`: fail compilation if `MAX_SIZE > N`.
+    pub max_size: Option<LitInt>,
+    /// `#[peek_poke(tag = u16)]`: the type of enum tags.
+    pub tag: Option<Ident>,
+    /// `#[repr(u16)]`: the type of enum tags, unless overridden by `tag`.
+    pub repr: Option<Ident>,
+    /// `#[repr(C)]` or `#[repr(transparent)]`: fields are laid out in order.
+    pub repr_c: bool,
+    /// `#[peek_poke(layout = std140)]`: serialize a struct in a GPU buffer
+    /// layout, `std140` or `std430`.
+    pub layout: Option<Ident>,
+}
+
+impl ContainerAttrs {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut res = ContainerAttrs {
+            repr: repr_int(attrs)?,
+            repr_c: repr_c(attrs)?,
+            ..ContainerAttrs::default()
+        };
+        for item in peek_poke_items(attrs)? {
+            match &*item.name.to_string() {
+                "max_size" => {
+                    let lit = item.lit_int()?;
+                    lit.base10_parse::<usize>()?;
+                    res.max_size = Some(lit);
+                }
+                "tag" => {
+                    let ty = item.ident()?;
+                    if !TAG_TYPES.contains(&&*ty.to_string()) {
+                        return Err(Error::new(ty.span(), "expected an integer type"));
+                    }
+                    res.tag = Some(ty);
+                }
+                "layout" => {
+                    let layout = item.ident()?;
+                    if layout != "std140" && layout != "std430" {
+                        return Err(Error::new(layout.span(), "expected `std140` or `std430`"));
+                    }
+                    res.layout = Some(layout);
+                }
+                _ => return Err(item.unknown()),
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// Attributes on a variant of the enum being derived.
+#[derive(Default)]
+pub struct VariantAttrs {
+    /// `#[peek_poke(other)]`: decode unknown tags as this variant.
+    pub other: bool,
+}
+
+impl VariantAttrs {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut res = VariantAttrs::default();
+        for item in peek_poke_items(attrs)? {
+            match &*item.name.to_string() {
+                "other" => {
+                    item.flag()?;
+                    res.other = true;
+                }
+                _ => return Err(item.unknown()),
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// An alternative encoding for a field, selected by a field attribute.
+#[derive(Clone)]
+pub enum FieldEncoding {
+    /// `#[peek_poke(varint)]`
+    Varint,
+    /// `#[peek_poke(zigzag)]`
+    ZigZag,
+    /// `#[peek_poke(bits)]` on a `[bool; N]`
+    BitArray,
+    /// `#[peek_poke(f16)]`
+    F16,
+    /// `#[peek_poke(fixed = i16, scale = 64)]`
+    Fixed { int: Ident, scale: LitInt },
+}
+
+impl FieldEncoding {
+    fn from_ident(name: &str) -> Option<Self> {
+        match name {
+            "varint" => Some(FieldEncoding::Varint),
+            "zigzag" => Some(FieldEncoding::ZigZag),
+            "f16" => Some(FieldEncoding::F16),
+            _ => None,
+        }
+    }
+
+    /// The type with the same layout as a field of type `ty` that implements
+    /// this encoding. The float encodings replace each `f32` in `ty`, through
+    /// `LossyF32`, whose implementations guarantee the layout, so apply to
+    /// e.g. `Point2D<f32>`, which is encoded as `Point2D<F16>`.
+    pub fn wrapper(&self, ty: &Type) -> TokenStream {
+        match *self {
+            FieldEncoding::Varint => quote!(peek_poke::Varint<#ty>),
+            FieldEncoding::ZigZag => quote!(peek_poke::ZigZag<#ty>),
+            FieldEncoding::BitArray => match *ty {
+                Type::Array(ref array) => {
+                    let len = &array.len;
+                    quote!(peek_poke::BitArray<{ #len }>)
+                }
+                _ => unreachable!("`bits` is only an encoding for arrays"),
+            },
+            FieldEncoding::F16 => quote_spanned! { ty.span() =>
+                <#ty as peek_poke::LossyF32<peek_poke::F16>>::Encoded
+            },
+            FieldEncoding::Fixed { ref int, ref scale } => quote_spanned! { ty.span() =>
+                <#ty as peek_poke::LossyF32<peek_poke::Fixed<#int, #scale>>>::Encoded
+            },
+        }
+    }
+}
+
+/// Whether `ty` is spelled `bool`.
+fn is_bool(ty: &Type) -> bool {
+    match *ty {
+        Type::Path(ref path) => path.qself.is_none() && path.path.is_ident("bool"),
+        _ => false,
+    }
+}
+
+/// Attributes on a field of the struct or enum being derived.
+#[derive(Default)]
+pub struct FieldAttrs {
+    pub encoding: Option<FieldEncoding>,
+    /// Whether the field is a `bool` that's packed into a byte with adjacent
+    /// bit fields: either spelled `bool`, or marked `#[peek_poke(bits)]`.
+    pub bits: bool,
+    /// `#[peek_poke(skip)]`: the field isn't serialized, and is set to its
+    /// default when deserialized.
+    pub skip: bool,
+    /// `#[peek_poke(default = "path")]`: the function a skipped field is set
+    /// to the result of, instead of `Default::default()`.
+    pub default: Option<Path>,
+    /// `#[peek_poke(with = "module")]`: the module whose `MAX_SIZE`,
+    /// `poke_into()` and `peek_from()` serialize the field.
+    pub with: Option<Path>,
+}
+
+impl FieldAttrs {
+    pub fn parse(attrs: &[Attribute], ty: &Type) -> Result<Self> {
+        let mut res = FieldAttrs::default();
+        let items = peek_poke_items(attrs)?;
+        let scale = items.iter().find(|item| item.name == "scale");
+        if let Some(scale) = scale {
+            if !items.iter().any(|item| item.name == "fixed") {
+                return Err(Error::new(scale.name.span(), "`scale` requires `fixed`"));
+            }
+        }
+        let skip = items.iter().find(|item| item.name == "skip");
+        if let Some(default) = items.iter().find(|item| item.name == "default") {
+            if skip.is_none() {
+                return Err(Error::new(default.name.span(), "`default` requires `skip`"));
+            }
+            res.default = Some(default.path()?);
+        }
+        if let Some(skip) = skip {
+            skip.flag()?;
+            if let Some(item) = items
+                .iter()
+                .find(|item| !["skip", "default"].contains(&&*item.name.to_string()))
+            {
+                return Err(Error::new(
+                    item.name.span(),
+                    "a skipped field can't have an encoding",
+                ));
+            }
+            res.skip = true;
+            return Ok(res);
+        }
+        if let Some(with) = items.iter().find(|item| item.name == "with") {
+            if let Some(item) = items.iter().find(|item| item.name != "with") {
+                return Err(Error::new(
+                    item.name.span(),
+                    "a field serialized `with` a module can't have an encoding",
+                ));
+            }
+            res.with = Some(with.path()?);
+            return Ok(res);
+        }
+        for item in items.iter().filter(|item| item.name != "scale") {
+            let name = item.name.to_string();
+            let encoding = match (&*name, ty) {
+                ("fixed", _) => {
+                    let int = item.ident()?;
+                    if !FIXED_TYPES.contains(&&*int.to_string()) {
+                        return Err(Error::new(
+                            int.span(),
+                            "expected an integer type of at most 32 bits",
+                        ));
+                    }
+                    let scale = scale
+                        .ok_or_else(|| Error::new(item.name.span(), "`fixed` requires `scale`"))?
+                        .lit_int()?;
+                    if scale.base10_parse::<u32>()? == 0 {
+                        return Err(Error::new(scale.span(), "`scale` must not be 0"));
+                    }
+                    Some(FieldEncoding::Fixed { int, scale })
+                }
+                ("bits", &Type::Array(_)) => Some(FieldEncoding::BitArray),
+                ("bits", _) => None,
+                _ => Some(FieldEncoding::from_ident(&name).ok_or_else(|| item.unknown())?),
+            };
+            if name != "fixed" {
+                item.flag()?;
+            }
+            if res.encoding.is_some() || res.bits {
+                return Err(Error::new(item.name.span(), "conflicting field encodings"));
+            }
+            res.bits = encoding.is_none();
+            res.encoding = encoding;
+        }
+        if res.encoding.is_none() && is_bool(ty) {
+            res.bits = true;
+        }
+        Ok(res)
+    }
+
+    /// Parse the attributes of every field, indexed by variant then field.
+    pub fn parse_all(s: &Structure) -> Result<Vec<Vec<Self>>> {
+        s.variants()
+            .iter()
+            .map(|vi| {
+                vi.bindings()
+                    .iter()
+                    .map(|bi| Self::parse(&bi.ast().attrs, &bi.ast().ty))
+                    .collect()
+            })
+            .collect()
+    }
+}
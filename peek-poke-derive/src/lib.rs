@@ -8,54 +8,336 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use proc_macro2::{Span, TokenStream};
-use quote::quote;
-use syn::{Ident, Index, TraitBound};
-use synstructure::{decl_derive, AddBounds, BindStyle, Structure};
-use unicode_xid::UnicodeXID;
-
-// Internal method for sanitizing an identifier for hygiene purposes.
-fn sanitize_ident(s: &str) -> Ident {
-    let mut res = String::with_capacity(s.len());
-    for mut c in s.chars() {
-        if !UnicodeXID::is_xid_continue(c) {
-            c = '_'
-        }
-        // Deduplicate consecutive _ characters.
-        if res.ends_with('_') && c == '_' {
-            continue;
-        }
-        res.push(c);
-    }
-    Ident::new(&res, Span::call_site())
-}
+use crate::attr::{ContainerAttrs, FieldAttrs, VariantAttrs};
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::{
+    parse_quote, spanned::Spanned, Error, Expr, ExprLit, ExprParen, ExprUnary, Ident, Index, Lit,
+    LitInt, Member, Path, TraitBound, UnOp,
+};
+use synstructure::{decl_derive, AddBounds, BindStyle, BindingInfo, Structure, VariantInfo};
+
+mod attr;
 
 /// Calculates size type for number of variants (used for enums)
 fn get_discriminant_size_type(len: usize) -> TokenStream {
-    if len <= <u8>::max_value() as usize {
+    if len <= <u8>::MAX as usize {
         quote! { u8 }
-    } else if len <= <u16>::max_value() as usize {
+    } else if len <= <u16>::MAX as usize {
         quote! { u16 }
     } else {
         quote! { u32 }
     }
 }
 
+/// The type and values of an enum's tags.
+struct Tags {
+    /// The integer type the tag is serialized as.
+    ty: TokenStream,
+    /// Definitions of a `const` per variant, holding the value of its tag.
+    consts: TokenStream,
+    /// The names of the `const`s, indexed by variant.
+    names: Vec<Ident>,
+    /// The index of the `#[peek_poke(other)]` variant, which unknown tags are
+    /// decoded as. It's either a unit variant or has a single field holding
+    /// the tag.
+    other: Option<usize>,
+}
+
+impl Tags {
+    /// Whether variant `i` is the `other` variant and holds the tag.
+    fn is_tag_carrying_other(&self, s: &Structure, i: usize) -> bool {
+        self.other == Some(i) && !s.variants()[i].bindings().is_empty()
+    }
+}
+
+/// The value of an explicit discriminant, if it's an integer literal.
+fn discriminant_value(expr: &Expr) -> Option<i128> {
+    match *expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(ref lit),
+            ..
+        }) => lit.base10_parse().ok(),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            ref expr,
+            ..
+        }) => discriminant_value(expr).map(|v| -v),
+        Expr::Paren(ExprParen { ref expr, .. }) => discriminant_value(expr),
+        _ => None,
+    }
+}
+
+/// Calculates the smallest type that holds the discriminants of an enum
+/// without `#[repr(...)]`, when any are set explicitly.
+fn get_discriminant_range_type(s: &Structure) -> syn::Result<TokenStream> {
+    let (mut min, mut max, mut next) = (0i128, 0i128, 0i128);
+    for vi in s.variants() {
+        let value = match vi.ast().discriminant {
+            Some((_, ref expr)) => discriminant_value(expr).ok_or_else(|| {
+                Error::new_spanned(
+                    expr,
+                    "can't infer the tag type from this discriminant, \
+                     add `#[repr(...)]` or `#[peek_poke(tag = ...)]`",
+                )
+            })?,
+            None => next,
+        };
+        min = min.min(value);
+        max = max.max(value);
+        next = value + 1;
+    }
+    let ty = if min >= 0 {
+        match max {
+            m if m <= i128::from(u8::MAX) => quote!(u8),
+            m if m <= i128::from(u16::MAX) => quote!(u16),
+            m if m <= i128::from(u32::MAX) => quote!(u32),
+            _ => quote!(u64),
+        }
+    } else {
+        match (min, max) {
+            (l, h) if l >= i128::from(i8::MIN) && h <= i128::from(i8::MAX) => quote!(i8),
+            (l, h) if l >= i128::from(i16::MIN) && h <= i128::from(i16::MAX) => quote!(i16),
+            (l, h) if l >= i128::from(i32::MIN) && h <= i128::from(i32::MAX) => quote!(i32),
+            _ => quote!(i64),
+        }
+    };
+    Ok(ty)
+}
+
+/// The tag type is set by `#[peek_poke(tag = ...)]`, then `#[repr(...)]`,
+/// otherwise it's the smallest that can hold the enum's discriminants, or
+/// the number of variants if none are explicit. Tag values follow the
+/// enum's discriminants, so are the variant's index unless set explicitly.
+fn derive_tags(s: &Structure, attrs: &ContainerAttrs) -> syn::Result<Tags> {
+    let explicit = s
+        .variants()
+        .iter()
+        .any(|vi| vi.ast().discriminant.is_some());
+    let ty = match attrs.tag.as_ref().or(attrs.repr.as_ref()) {
+        Some(ty) => quote!(#ty),
+        None if explicit => get_discriminant_range_type(s)?,
+        None => get_discriminant_size_type(s.variants().len()),
+    };
+    let mut consts = quote!();
+    let mut names = Vec::<Ident>::new();
+    let mut other = None;
+    for (i, vi) in s.variants().iter().enumerate() {
+        if !is_struct(s) && VariantAttrs::parse(vi.ast().attrs)?.other {
+            if other.is_some() {
+                return Err(Error::new(
+                    vi.ast().ident.span(),
+                    "only one variant can be `#[peek_poke(other)]`",
+                ));
+            }
+            if vi.bindings().len() > 1 {
+                return Err(Error::new(
+                    vi.ast().ident.span(),
+                    "an `#[peek_poke(other)]` variant must be a unit variant or have a single field holding the tag",
+                ));
+            }
+            other = Some(i);
+        }
+        let name = Ident::new(&format!("__TAG_{}", i), Span::call_site());
+        let value = match vi.ast().discriminant {
+            Some((_, expr)) => quote_spanned!(expr.span() => #expr),
+            None => match names.last() {
+                Some(prev) => quote!(#prev + 1),
+                None => quote!(0),
+            },
+        };
+        consts.extend(quote! {
+            const #name: #ty = #value;
+        });
+        names.push(name);
+    }
+    Ok(Tags {
+        ty,
+        consts,
+        names,
+        other,
+    })
+}
+
 fn is_struct(s: &Structure) -> bool {
     // a single variant with no prefix is 'struct'
-    match &s.variants()[..] {
-        [v] if v.prefix.is_none() => true,
-        _ => false,
+    matches!(s.variants(), [v] if v.prefix.is_none())
+}
+
+/// The type a field is serialized as: its own type, or the wrapper selected
+/// by a `#[peek_poke(...)]` attribute.
+fn encoded_ty(bi: &BindingInfo, attrs: &FieldAttrs) -> TokenStream {
+    let ty = &bi.ast().ty;
+    match attrs.encoding {
+        Some(ref encoding) => encoding.wrapper(ty),
+        None => quote!(#ty),
     }
 }
 
-fn derive_max_size(s: &Structure) -> TokenStream {
-    let max_size = s.variants().iter().fold(quote!(0), |acc, vi| {
-        let variant_size = vi.bindings().iter().fold(quote!(0), |acc, bi| {
-            // compute size of each variant by summing the sizes of its bindings
+/// Casts `ptr`, a `*mut` pointer to the field bound to `bi`, to a pointer to
+/// its encoded type. The wrappers are all `#[repr(transparent)]`, and
+/// `LossyF32` guarantees the layout of the float encodings, so they can be
+/// read in place.
+fn encoded_output(bi: &BindingInfo, attrs: &FieldAttrs, ptr: TokenStream) -> TokenStream {
+    match attrs.encoding {
+        Some(ref encoding) => {
             let ty = &bi.ast().ty;
-            quote!(#acc + <#ty>::MAX_SIZE)
-        });
+            let encoded_ty = encoding.wrapper(ty);
+            quote!(#ptr as *mut #ty as *mut #encoded_ty)
+        }
+        None => ptr,
+    }
+}
+
+/// The members naming the fields of `vi`, for field access expressions.
+fn members(vi: &VariantInfo) -> Vec<Member> {
+    vi.ast()
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match field.ident {
+            Some(ref ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        })
+        .collect()
+}
+
+/// Binds a `*mut` pointer to each field of the struct pointed to by `output`,
+/// so that the fields can be deserialized in place without being initialized
+/// first.
+fn field_ptrs(vi: &VariantInfo) -> TokenStream {
+    let bindings = vi.bindings();
+    let members = members(vi);
+    quote! {
+        #(let #bindings = ::core::ptr::addr_of_mut!((*output).#members);)*
+    }
+}
+
+/// A field, a field serialized by a `#[peek_poke(with = "module")]` module, or
+/// a run of up to eight consecutive bit fields, which are packed into a byte,
+/// the first in the least significant bit.
+enum Segment<'a> {
+    Field(&'a BindingInfo<'a>, &'a FieldAttrs),
+    With(&'a BindingInfo<'a>, &'a Path),
+    Bits(Vec<&'a BindingInfo<'a>>),
+}
+
+/// Groups the fields of a variant into the segments they're serialized as.
+/// Skipped fields aren't serialized, so don't interrupt a run of bit fields.
+fn segments<'a>(bindings: &'a [BindingInfo<'a>], attrs: &'a [FieldAttrs]) -> Vec<Segment<'a>> {
+    let mut segments = Vec::new();
+    for (bi, attrs) in bindings.iter().zip(attrs).filter(|(_, attrs)| !attrs.skip) {
+        match segments.last_mut() {
+            Some(Segment::Bits(run)) if attrs.bits && run.len() < 8 => run.push(bi),
+            _ if attrs.bits => segments.push(Segment::Bits(vec![bi])),
+            _ => match attrs.with {
+                Some(ref module) => segments.push(Segment::With(bi, module)),
+                None => segments.push(Segment::Field(bi, attrs)),
+            },
+        }
+    }
+    segments
+}
+
+/// Sets each skipped field of a variant to its default, either writing
+/// through their `*mut` bindings or declaring them as locals.
+fn init_skipped(bindings: &[BindingInfo], attrs: &[FieldAttrs], declare: bool) -> TokenStream {
+    bindings
+        .iter()
+        .zip(attrs)
+        .filter(|(_, attrs)| attrs.skip)
+        .map(|(bi, attrs)| {
+            let ty = &bi.ast().ty;
+            let value = match attrs.default {
+                Some(ref path) => quote_spanned!(path.span() => #path()),
+                None => quote_spanned!(ty.span() => ::core::default::Default::default()),
+            };
+            if declare {
+                quote!(let #bi: #ty = #value;)
+            } else {
+                quote!(#bi.write(#value);)
+            }
+        })
+        .collect()
+}
+
+fn bit_mask(i: usize) -> Literal {
+    Literal::u8_suffixed(1 << i)
+}
+
+/// Packs the bit fields bound to `run`, which are references, into a byte.
+fn pack_bits(run: &[&BindingInfo]) -> TokenStream {
+    let bits = run.iter().enumerate().map(|(i, bi)| {
+        let mask = bit_mask(i);
+        quote_spanned! { bi.ast().ty.span() =>
+            {
+                let bit: bool = *#bi;
+                bit as u8 * #mask
+            }
+        }
+    });
+    quote!(#(#bits)|*)
+}
+
+/// Unpacks `byte` into the bit fields bound to `run`, either writing through
+/// their `*mut` bindings or declaring them as locals.
+fn unpack_bits(run: &[&BindingInfo], declare: bool) -> TokenStream {
+    run.iter()
+        .enumerate()
+        .map(|(i, bi)| {
+            let mask = bit_mask(i);
+            let ty = &bi.ast().ty;
+            if declare {
+                quote_spanned!(ty.span() => let #bi: #ty = byte & #mask != 0;)
+            } else {
+                quote_spanned!(ty.span() => #bi.write(byte & #mask != 0);)
+            }
+        })
+        .collect()
+}
+
+/// Reads the byte holding the bit fields bound to `run` and unpacks it.
+fn peek_bits(run: &[&BindingInfo], declare: bool) -> TokenStream {
+    let unpack = unpack_bits(run, declare);
+    quote! {
+        let (byte, bytes) = peek_poke::peek_from_uninit::<u8>(bytes);
+        #unpack
+    }
+}
+
+/// Fallible version of `peek_bits()`.
+fn try_peek_bits(run: &[&BindingInfo], declare: bool, type_name: &str) -> TokenStream {
+    let len = run.len() as u32;
+    let unpack = unpack_bits(run, declare);
+    quote! {
+        let (byte, bytes) = peek_poke::try_peek_from_uninit::<u8>(bytes, end)?;
+        peek_poke::check_bit_padding(byte, #len, #type_name)?;
+        #unpack
+    }
+}
+
+fn derive_max_size(s: &Structure, tags: &Tags, field_attrs: &[Vec<FieldAttrs>]) -> TokenStream {
+    let variants = s.variants().iter().zip(field_attrs).enumerate();
+    let max_size = variants.fold(quote!(0), |acc, (i, (vi, attrs))| {
+        // The tag-carrying `other` variant is serialized as just its tag.
+        if tags.is_tag_carrying_other(s, i) {
+            return acc;
+        }
+        let variant_size =
+            segments(vi.bindings(), attrs)
+                .into_iter()
+                .fold(quote!(0), |acc, segment| {
+                    // compute size of each variant by summing the sizes of its bindings
+                    let size = match segment {
+                        Segment::Field(bi, attrs) => {
+                            let ty = encoded_ty(bi, attrs);
+                            quote!(<#ty>::MAX_SIZE)
+                        }
+                        Segment::With(_, module) => quote!(#module::MAX_SIZE),
+                        Segment::Bits(_) => quote!(<u8>::MAX_SIZE),
+                    };
+                    quote!(#acc + #size)
+                });
 
         // find the maximum of each variant
         quote! {
@@ -66,9 +348,9 @@ fn derive_max_size(s: &Structure) -> TokenStream {
     let body = if is_struct(s) {
         max_size
     } else {
-        let discriminant_size_type = get_discriminant_size_type(s.variants().len());
+        let tag_ty = &tags.ty;
         quote! {
-            #discriminant_size_type ::MAX_SIZE + #max_size
+            <#tag_ty>::MAX_SIZE + #max_size
         }
     };
 
@@ -77,16 +359,93 @@ fn derive_max_size(s: &Structure) -> TokenStream {
     }
 }
 
-fn derive_peek_from_for_enum(s: &mut Structure) -> TokenStream {
+/// Emits a check that `MAX_SIZE` fits in the `#[peek_poke(max_size = N)]`
+/// budget.
+///
+/// A failing `assert!` in a const can only report a fixed message, so the
+/// sizes are reported through array length mismatches instead: one for the
+/// total, pointing at the attribute, and one for each field (and the enum
+/// tag), pointing at its type, which are only non-zero when over budget.
+fn derive_max_size_budget(
+    s: &Structure,
+    tags: &Tags,
+    field_attrs: &[Vec<FieldAttrs>],
+    budget: &LitInt,
+) -> syn::Result<TokenStream> {
+    let ast = s.ast();
+    if !ast.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            budget,
+            "`max_size` can't be checked for generic types",
+        ));
+    }
+    let name = &ast.ident;
+    let budget_val = budget.base10_parse::<usize>()?;
+
+    let mut sizes = Vec::new();
+    if !is_struct(s) {
+        let tag_ty = &tags.ty;
+        sizes.push(quote_spanned! { name.span() =>
+            const _: [(); 0] = [(); (OVER as usize) * <#tag_ty>::MAX_SIZE];
+        });
+    }
+    let variants = s.variants().iter().zip(field_attrs).enumerate();
+    let segments = variants
+        .filter(|&(i, _)| !tags.is_tag_carrying_other(s, i))
+        .flat_map(|(_, (vi, attrs))| segments(vi.bindings(), attrs));
+    for segment in segments {
+        let (span, size) = match segment {
+            Segment::Field(bi, attrs) => {
+                let ty = encoded_ty(bi, attrs);
+                (
+                    bi.ast().ty.span(),
+                    quote!(<#ty as peek_poke::Poke>::MAX_SIZE),
+                )
+            }
+            Segment::With(bi, module) => (bi.ast().ty.span(), quote!(#module::MAX_SIZE)),
+            Segment::Bits(run) => (
+                run[0].ast().ty.span(),
+                quote!(<u8 as peek_poke::Poke>::MAX_SIZE),
+            ),
+        };
+        sizes.push(quote_spanned! { span =>
+            const _: [(); 0] = [(); (OVER as usize) * #size];
+        });
+    }
+    let total = quote_spanned! { budget.span() =>
+        const _: [(); #budget_val] = [(); if OVER { MAX_SIZE } else { #budget_val }];
+    };
+    let message = format!(
+        "{}::MAX_SIZE exceeds the budget of {} bytes set by `#[peek_poke(max_size = {})]`",
+        name, budget_val, budget_val
+    );
+
+    Ok(quote! {
+        const _: () = {
+            extern crate peek_poke;
+
+            const MAX_SIZE: usize = <#name as peek_poke::Poke>::MAX_SIZE;
+            const OVER: bool = MAX_SIZE > #budget_val;
+            #total
+            #(#sizes)*
+            assert!(!OVER, #message);
+        };
+    })
+}
+
+fn derive_peek_from_for_enum(
+    s: &mut Structure,
+    tags: &Tags,
+    field_attrs: &[Vec<FieldAttrs>],
+) -> TokenStream {
     assert!(!is_struct(s));
     s.bind_with(|_| BindStyle::Move);
 
-    let num_variants = s.variants().len();
-    let discriminant_size_type = get_discriminant_size_type(num_variants);
     let body = s
         .variants()
         .iter()
         .enumerate()
+        .filter(|&(i, _)| tags.other != Some(i))
         .fold(quote!(), |acc, (i, vi)| {
             let bindings = vi
                 .bindings()
@@ -94,13 +453,42 @@ fn derive_peek_from_for_enum(s: &mut Structure) -> TokenStream {
                 .map(|bi| quote!(#bi))
                 .collect::<Vec<_>>();
 
-            let variant_pat = Index::from(i);
-            let poke_exprs = bindings.iter().fold(quote!(), |acc, bi| {
+            let variant_pat = &tags.names[i];
+            let segments = segments(vi.bindings(), &field_attrs[i]);
+            let peek_exprs = segments.into_iter().fold(quote!(), |acc, segment| {
+                let peek_expr = match segment {
+                    Segment::Field(bi, attrs) if attrs.encoding.is_some() => {
+                        let ty = &bi.ast().ty;
+                        let encoded_ty = encoded_ty(bi, attrs);
+                        let output = encoded_output(bi, attrs, quote!(#bi.as_mut_ptr()));
+                        quote! {
+                            let mut #bi = ::core::mem::MaybeUninit::<#ty>::uninit();
+                            let bytes = <#encoded_ty>::peek_from(
+                                bytes,
+                                peek_poke::uninit_field(#output),
+                            );
+                            let #bi = #bi.assume_init();
+                        }
+                    }
+                    Segment::Field(bi, _) => {
+                        quote!(let (#bi, bytes) = peek_poke::peek_from_uninit(bytes);)
+                    }
+                    Segment::With(bi, module) => {
+                        let ty = &bi.ast().ty;
+                        quote! {
+                            let mut #bi = ::core::mem::MaybeUninit::<#ty>::uninit();
+                            let bytes = #module::peek_from(bytes, &mut #bi);
+                            let #bi = #bi.assume_init();
+                        }
+                    }
+                    Segment::Bits(run) => peek_bits(&run, true),
+                };
                 quote! {
                     #acc
-                    let (#bi, bytes) = peek_poke::peek_from_default(bytes);
+                    #peek_expr
                 }
             });
+            let skipped = init_skipped(vi.bindings(), &field_attrs[i], true);
             let construct = vi.construct(|_, i| {
                 let bi = &bindings[i];
                 quote!(#bi)
@@ -109,82 +497,314 @@ fn derive_peek_from_for_enum(s: &mut Structure) -> TokenStream {
             quote! {
                 #acc
                 #variant_pat => {
-                    #poke_exprs
-                    *output = #construct;
+                    #peek_exprs
+                    #skipped
+                    output.write(#construct);
                     bytes
                 }
             }
         });
 
     let type_name = s.ast().ident.to_string();
-    let max_tag_value = num_variants - 1;
+    let tag_ty = &tags.ty;
+    let tag_consts = &tags.consts;
+    let unknown_tag_arm = match tags.other {
+        Some(i) => {
+            let construct = s.variants()[i].construct(|_, _| quote!(unknown_tag));
+            quote! {
+                unknown_tag => {
+                    output.write(#construct);
+                    bytes
+                }
+            }
+        }
+        None => quote! {
+            invalid_tag => {
+                panic!("WRDL: memory corruption detected while parsing {} - invalid enum tag {}",
+                    #type_name, invalid_tag);
+            }
+        },
+    };
 
     quote! {
         #[inline(always)]
-        unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-            let (variant, bytes) = peek_poke::peek_from_default::<#discriminant_size_type>(bytes);
-            match variant {
+        unsafe fn peek_from(bytes: *const u8, output: &mut ::core::mem::MaybeUninit<Self>) -> *const u8 {
+            #tag_consts
+            let start = bytes;
+            let (variant, bytes) = peek_poke::peek_from_uninit::<#tag_ty>(bytes);
+            let end_ptr = match variant {
                 #body
-                out_of_range_tag => {
-                    panic!("WRDL: memory corruption detected while parsing {} - enum tag should be <= {}, but was {}",
-                        #type_name, #max_tag_value, out_of_range_tag);
-                }
-            }
+                #unknown_tag_arm
+            };
+            peek_poke::check_max_size::<Self>("peek_from", start, end_ptr);
+            end_ptr
         }
     }
 }
 
-fn derive_peek_from_for_struct(s: &mut Structure) -> TokenStream {
-    assert!(is_struct(&s));
+fn derive_peek_from_for_struct(
+    s: &mut Structure,
+    field_attrs: &[Vec<FieldAttrs>],
+    verbatim: &Option<Vec<TokenStream>>,
+) -> TokenStream {
+    assert!(is_struct(s));
 
-    s.variants_mut()[0].bind_with(|_| BindStyle::RefMut);
-    let pat = s.variants()[0].pat();
-    let peek_exprs = s.variants()[0].bindings().iter().fold(quote!(), |acc, bi| {
-        let ty = &bi.ast().ty;
+    let field_ptrs = field_ptrs(&s.variants()[0]);
+    let skipped = init_skipped(s.variants()[0].bindings(), &field_attrs[0], false);
+    let segments = segments(s.variants()[0].bindings(), &field_attrs[0]);
+    let peek_exprs = segments.into_iter().fold(quote!(), |acc, segment| {
+        let peek_expr = match segment {
+            Segment::Field(bi, attrs) => {
+                let ty = encoded_ty(bi, attrs);
+                let output = encoded_output(bi, attrs, quote!(#bi));
+                quote!(let bytes = <#ty>::peek_from(bytes, peek_poke::uninit_field(#output));)
+            }
+            Segment::With(bi, module) => {
+                quote!(let bytes = #module::peek_from(bytes, peek_poke::uninit_field(#bi));)
+            }
+            Segment::Bits(run) => peek_bits(&run, false),
+        };
         quote! {
             #acc
-            let bytes = <#ty>::peek_from(bytes, #bi);
+            #peek_expr
         }
     });
 
-    let body = quote! {
-        #pat => {
+    let fast_path = verbatim_fast_path(verbatim, VerbatimOp::Peek);
+
+    quote! {
+        #[inline(always)]
+        unsafe fn peek_from(bytes: *const u8, output: &mut ::core::mem::MaybeUninit<Self>) -> *const u8 {
+            #fast_path
+            let start = bytes;
+            let output = output.as_mut_ptr();
+            #field_ptrs
             #peek_exprs
+            #skipped
+            peek_poke::check_max_size::<Self>("peek_from", start, bytes);
             bytes
         }
+    }
+}
+
+fn derive_try_peek_from_for_enum(
+    s: &mut Structure,
+    tags: &Tags,
+    field_attrs: &[Vec<FieldAttrs>],
+) -> TokenStream {
+    assert!(!is_struct(s));
+    s.bind_with(|_| BindStyle::Move);
+    let type_name = s.ast().ident.to_string();
+
+    let body = s
+        .variants()
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| tags.other != Some(i))
+        .fold(quote!(), |acc, (i, vi)| {
+            let bindings = vi
+                .bindings()
+                .iter()
+                .map(|bi| quote!(#bi))
+                .collect::<Vec<_>>();
+
+            let variant_pat = &tags.names[i];
+            let segments = segments(vi.bindings(), &field_attrs[i]);
+            let peek_exprs = segments.into_iter().fold(quote!(), |acc, segment| {
+                let peek_expr = match segment {
+                    Segment::Field(bi, attrs) if attrs.encoding.is_some() => {
+                        let ty = &bi.ast().ty;
+                        let encoded_ty = encoded_ty(bi, attrs);
+                        let output = encoded_output(bi, attrs, quote!(#bi.as_mut_ptr()));
+                        quote! {
+                            let mut #bi = ::core::mem::MaybeUninit::<#ty>::uninit();
+                            let bytes = <#encoded_ty>::try_peek_from(
+                                bytes, end,
+                                peek_poke::uninit_field(#output),
+                            )?;
+                            let #bi = #bi.assume_init();
+                        }
+                    }
+                    Segment::Field(bi, _) => {
+                        quote!(let (#bi, bytes) = peek_poke::try_peek_from_uninit(bytes, end)?;)
+                    }
+                    Segment::With(bi, module) => {
+                        let ty = &bi.ast().ty;
+                        quote! {
+                            let mut #bi = ::core::mem::MaybeUninit::<#ty>::uninit();
+                            let bytes = peek_poke::try_peek_with(bytes, end, #module::MAX_SIZE, |bytes| {
+                                #module::peek_from(bytes, &mut #bi)
+                            })?;
+                            let #bi = #bi.assume_init();
+                        }
+                    }
+                    Segment::Bits(run) => try_peek_bits(&run, true, &type_name),
+                };
+                quote! {
+                    #acc
+                    #peek_expr
+                }
+            });
+            let skipped = init_skipped(vi.bindings(), &field_attrs[i], true);
+            let construct = vi.construct(|_, i| {
+                let bi = &bindings[i];
+                quote!(#bi)
+            });
+
+            quote! {
+                #acc
+                #variant_pat => {
+                    #peek_exprs
+                    #skipped
+                    output.write(#construct);
+                    Ok(bytes)
+                }
+            }
+        });
+
+    let tag_ty = &tags.ty;
+    let tag_consts = &tags.consts;
+    let unknown_tag_arm = match tags.other {
+        Some(i) => {
+            let construct = s.variants()[i].construct(|_, _| quote!(unknown_tag));
+            quote! {
+                unknown_tag => {
+                    output.write(#construct);
+                    Ok(bytes)
+                }
+            }
+        }
+        None => quote! {
+            invalid_tag => Err(peek_poke::PeekError::InvalidTag {
+                type_name: #type_name,
+                tag: invalid_tag as u64,
+            }),
+        },
     };
 
     quote! {
         #[inline(always)]
-        unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-            match &mut (*output) {
+        unsafe fn try_peek_from(
+            bytes: *const u8,
+            end: *const u8,
+            output: &mut ::core::mem::MaybeUninit<Self>,
+        ) -> Result<*const u8, peek_poke::PeekError> {
+            #tag_consts
+            let start = bytes;
+            let (variant, bytes) = peek_poke::try_peek_from_uninit::<#tag_ty>(bytes, end)?;
+            let end_ptr = match variant {
                 #body
+                #unknown_tag_arm
+            }?;
+            peek_poke::check_max_size::<Self>("try_peek_from", start, end_ptr);
+            Ok(end_ptr)
+        }
+    }
+}
+
+fn derive_try_peek_from_for_struct(
+    s: &mut Structure,
+    field_attrs: &[Vec<FieldAttrs>],
+    verbatim: &Option<Vec<TokenStream>>,
+) -> TokenStream {
+    assert!(is_struct(s));
+
+    let field_ptrs = field_ptrs(&s.variants()[0]);
+    let skipped = init_skipped(s.variants()[0].bindings(), &field_attrs[0], false);
+    let type_name = s.ast().ident.to_string();
+    let segments = segments(s.variants()[0].bindings(), &field_attrs[0]);
+    let peek_exprs = segments.into_iter().fold(quote!(), |acc, segment| {
+        let peek_expr = match segment {
+            Segment::Field(bi, attrs) => {
+                let ty = encoded_ty(bi, attrs);
+                let output = encoded_output(bi, attrs, quote!(#bi));
+                quote! {
+                    let bytes = <#ty>::try_peek_from(bytes, end, peek_poke::uninit_field(#output))?;
+                }
             }
+            Segment::With(bi, module) => quote! {
+                let bytes = peek_poke::try_peek_with(bytes, end, #module::MAX_SIZE, |bytes| {
+                    #module::peek_from(bytes, peek_poke::uninit_field(#bi))
+                })?;
+            },
+            Segment::Bits(run) => try_peek_bits(&run, false, &type_name),
+        };
+        quote! {
+            #acc
+            #peek_expr
+        }
+    });
+
+    let fast_path = verbatim_fast_path(verbatim, VerbatimOp::TryPeek);
+
+    quote! {
+        #[inline(always)]
+        unsafe fn try_peek_from(
+            bytes: *const u8,
+            end: *const u8,
+            output: &mut ::core::mem::MaybeUninit<Self>,
+        ) -> Result<*const u8, peek_poke::PeekError> {
+            #fast_path
+            let start = bytes;
+            let output = output.as_mut_ptr();
+            #field_ptrs
+            #peek_exprs
+            #skipped
+            peek_poke::check_max_size::<Self>("try_peek_from", start, bytes);
+            Ok(bytes)
         }
     }
 }
 
-fn derive_poke_into(s: &Structure) -> TokenStream {
-    let is_struct = is_struct(&s);
-    let discriminant_size_type = get_discriminant_size_type(s.variants().len());
+fn derive_poke_into(
+    s: &Structure,
+    tags: &Tags,
+    field_attrs: &[Vec<FieldAttrs>],
+    verbatim: &Option<Vec<TokenStream>>,
+) -> TokenStream {
+    let is_struct = is_struct(s);
+    let tag_ty = &tags.ty;
     let body = s
         .variants()
         .iter()
         .enumerate()
         .fold(quote!(), |acc, (i, vi)| {
-            let init = if !is_struct {
-                let index = Index::from(i);
+            let variant_pat = vi.pat();
+            let init = if tags.is_tag_carrying_other(s, i) {
+                // Write the tag it was decoded from instead of its own.
+                let bi = &vi.bindings()[0];
+                return quote! {
+                    #acc
+                    #variant_pat => <#tag_ty>::poke_into(#bi, bytes),
+                };
+            } else if !is_struct {
+                let tag = &tags.names[i];
                 quote! {
-                    let bytes = #discriminant_size_type::poke_into(&#index, bytes);
+                    let bytes = <#tag_ty>::poke_into(&#tag, bytes);
                 }
             } else {
                 quote!()
             };
-            let variant_pat = vi.pat();
-            let poke_exprs = vi.bindings().iter().fold(init, |acc, bi| {
+            let segments = segments(vi.bindings(), &field_attrs[i]);
+            let poke_exprs = segments.into_iter().fold(init, |acc, segment| {
+                let poke_expr = match segment {
+                    Segment::Field(bi, attrs) if attrs.encoding.is_some() => {
+                        let ty = &bi.ast().ty;
+                        let encoded_ty = encoded_ty(bi, attrs);
+                        quote! {
+                            peek_poke::Poke::poke_into(&*(#bi as *const #ty as *const #encoded_ty), bytes)
+                        }
+                    }
+                    Segment::Field(bi, _) => quote!(#bi.poke_into(bytes)),
+                    Segment::With(bi, module) => quote!(#module::poke_into(#bi, bytes)),
+                    Segment::Bits(run) => {
+                        let byte = pack_bits(&run);
+                        quote!(<u8>::poke_into(&(#byte), bytes))
+                    }
+                };
                 quote! {
                     #acc
-                    let bytes = #bi.poke_into(bytes);
+                    let bytes = #poke_expr;
                 }
             });
 
@@ -197,66 +817,479 @@ fn derive_poke_into(s: &Structure) -> TokenStream {
             }
         });
 
+    let tag_consts = if is_struct {
+        quote!()
+    } else {
+        tags.consts.clone()
+    };
+
+    let fast_path = verbatim_fast_path(verbatim, VerbatimOp::Poke);
+
     quote! {
         #[inline(always)]
         unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
-            match &*self {
+            #fast_path
+            #tag_consts
+            let end_ptr = match &*self {
                 #body
+            };
+            peek_poke::check_max_size::<Self>("poke_into", bytes, end_ptr);
+            end_ptr
+        }
+    }
+}
+
+/// The encoded types of the fields of a struct that could be verbatim,
+/// because its fields are laid out in order and none are packed bits,
+/// skipped or serialized by a `with` module.
+fn verbatim_fields(
+    s: &Structure,
+    attrs: &ContainerAttrs,
+    field_attrs: &[Vec<FieldAttrs>],
+) -> Option<Vec<TokenStream>> {
+    if !is_struct(s) || !attrs.repr_c || field_attrs[0].iter().any(|attrs| attrs.skip) {
+        return None;
+    }
+    segments(s.variants()[0].bindings(), &field_attrs[0])
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Field(bi, attrs) => Some(encoded_ty(bi, attrs)),
+            Segment::With(..) | Segment::Bits(_) => None,
+        })
+        .collect()
+}
+
+/// `Poke::VERBATIM`, which holds if all the fields are verbatim and there's no
+/// padding between them.
+fn derive_verbatim(fields: &Option<Vec<TokenStream>>) -> TokenStream {
+    match *fields {
+        Some(ref fields) => quote! {
+            const VERBATIM: bool = #(<#fields>::VERBATIM &&)*
+                ::core::mem::size_of::<Self>() == <Self as peek_poke::Poke>::MAX_SIZE;
+        },
+        None => quote!(),
+    }
+}
+
+/// A fast path that copies a verbatim struct as a whole instead of field by
+/// field. `VERBATIM` is a constant, so only one path is compiled.
+fn verbatim_fast_path(fields: &Option<Vec<TokenStream>>, op: VerbatimOp) -> TokenStream {
+    if fields.is_none() {
+        return quote!();
+    }
+    let copy = match op {
+        VerbatimOp::Poke => quote! {
+            ::core::ptr::copy_nonoverlapping(self as *const Self as *const u8, bytes, size);
+            return bytes.add(size);
+        },
+        VerbatimOp::Peek => quote! {
+            ::core::ptr::copy_nonoverlapping(bytes, output.as_mut_ptr() as *mut u8, size);
+            return bytes.add(size);
+        },
+        VerbatimOp::TryPeek => quote! {
+            if (end as usize - bytes as usize) < size {
+                return Err(peek_poke::PeekError::UnexpectedEnd);
             }
+            ::core::ptr::copy_nonoverlapping(bytes, output.as_mut_ptr() as *mut u8, size);
+            return Ok(bytes.add(size));
+        },
+    };
+    quote! {
+        if <Self as peek_poke::Poke>::VERBATIM {
+            let size = ::core::mem::size_of::<Self>();
+            #copy
         }
     }
 }
 
-fn peek_poke_derive(mut s: Structure) -> TokenStream {
+#[derive(Clone, Copy)]
+enum VerbatimOp {
+    Poke,
+    Peek,
+    TryPeek,
+}
+
+/// Items implementing `Poke::NICHE` and the methods that go with it, for the
+/// `Poke`, `Peek` and `TryPeek` impls.
+///
+/// `Option<Self>` encodes `None` as the largest value of the tag type, unless
+/// it's a variant's tag. Enums with an `other` variant have no unused tags.
+fn derive_niche(s: &Structure, tags: &Tags) -> (TokenStream, TokenStream, TokenStream) {
+    if is_struct(s) || tags.other.is_some() {
+        return (quote!(), quote!(), quote!());
+    }
+
+    let tag_ty = &tags.ty;
+    let tag_consts = &tags.consts;
+    let names = &tags.names;
+    let unused = if names.is_empty() {
+        quote!(true)
+    } else {
+        quote!(#(#names != <#tag_ty>::MAX)&&*)
+    };
+
+    let poke = quote! {
+        const NICHE: bool = {
+            #tag_consts
+            #unused
+        };
+
+        #[inline(always)]
+        unsafe fn poke_niche_into(bytes: *mut u8) -> *mut u8 {
+            <#tag_ty>::poke_into(&<#tag_ty>::MAX, bytes)
+        }
+    };
+    let peek = quote! {
+        #[inline(always)]
+        unsafe fn peek_niche_from(bytes: *const u8) -> Option<*const u8> {
+            let (tag, bytes) = peek_poke::peek_from_uninit::<#tag_ty>(bytes);
+            if tag == <#tag_ty>::MAX {
+                Some(bytes)
+            } else {
+                None
+            }
+        }
+    };
+    let try_peek = quote! {
+        #[inline(always)]
+        unsafe fn try_peek_niche_from(
+            bytes: *const u8,
+            end: *const u8,
+        ) -> Result<Option<*const u8>, peek_poke::PeekError> {
+            let (tag, bytes) = peek_poke::try_peek_from_uninit::<#tag_ty>(bytes, end)?;
+            Ok(if tag == <#tag_ty>::MAX {
+                Some(bytes)
+            } else {
+                None
+            })
+        }
+    };
+    (poke, peek, try_peek)
+}
+
+/// With `#[peek_poke(layout = ...)]`, a struct implements `GpuLayout` and is
+/// serialized in that layout instead of densely, with each field at an offset
+/// that's a multiple of its alignment.
+fn derive_gpu_layout(
+    s: &Structure,
+    attrs: &ContainerAttrs,
+    field_attrs: &[Vec<FieldAttrs>],
+    layout: &Ident,
+    derive: Derive,
+) -> syn::Result<TokenStream> {
+    let ast = s.ast();
+    if !is_struct(s) {
+        return Err(Error::new(
+            layout.span(),
+            "`layout` is only supported on structs",
+        ));
+    }
+    if !ast.generics.params.is_empty() {
+        return Err(Error::new(
+            layout.span(),
+            "`layout` isn't supported on generic types",
+        ));
+    }
+    // `GpuLayout` both pokes and peeks, so it's implemented by the `Poke`
+    // side, and a `Peek` derive can't tell whether one runs alongside it.
+    if derive == Derive::Peek {
+        return Err(Error::new(
+            layout.span(),
+            "`layout` requires `#[derive(PeekPoke)]` or `#[derive(Poke)]`, which implement `GpuLayout`",
+        ));
+    }
+    if let Some(ref budget) = attrs.max_size {
+        return Err(Error::new_spanned(
+            budget,
+            "`max_size` can't be combined with `layout`",
+        ));
+    }
+    if field_attrs[0]
+        .iter()
+        .any(|attrs| attrs.encoding.is_some() || attrs.with.is_some())
+    {
+        return Err(Error::new(
+            layout.span(),
+            "field encodings can't be combined with `layout`",
+        ));
+    }
+    if field_attrs[0].iter().any(|attrs| attrs.skip) {
+        return Err(Error::new(
+            layout.span(),
+            "skipped fields can't be combined with `layout`",
+        ));
+    }
+
+    let name = &ast.ident;
+    let layout_ty = if layout == "std140" {
+        quote!(peek_poke::Std140)
+    } else {
+        quote!(peek_poke::Std430)
+    };
+    let gpu = quote!(peek_poke::GpuLayout<#layout_ty>);
+    let vi = &s.variants()[0];
+    let tys = vi
+        .bindings()
+        .iter()
+        .map(|bi| &bi.ast().ty)
+        .collect::<Vec<_>>();
+    let members = members(vi);
+    let len = tys.len();
+    let indices = (0..len).collect::<Vec<_>>();
+    let offsets = quote! {
+        const LAYOUT: ([usize; #len], usize) =
+            peek_poke::field_offsets([#((<#tys as #gpu>::ALIGN, <#tys as #gpu>::SIZE)),*]);
+    };
+    let field_offsets = if len == 0 { quote!() } else { offsets.clone() };
+
+    let poke_impls = quote! {
+        const _: () = {
+            extern crate peek_poke;
+
+            unsafe impl #gpu for #name {
+                const ALIGN: usize = {
+                    let align = <#layout_ty as peek_poke::Layout>::MIN_AGGREGATE_ALIGN;
+                    #(let align = peek_poke::max(align, <#tys as #gpu>::ALIGN);)*
+                    align
+                };
+                const SIZE: usize = {
+                    #offsets
+                    LAYOUT.1.next_multiple_of(<Self as #gpu>::ALIGN)
+                };
+
+                #[inline(always)]
+                unsafe fn poke_gpu(&self, bytes: *mut u8) {
+                    #field_offsets
+                    bytes.write_bytes(0, <Self as #gpu>::SIZE);
+                    #(<#tys as #gpu>::poke_gpu(&self.#members, bytes.add(LAYOUT.0[#indices]));)*
+                }
+
+                #[inline(always)]
+                unsafe fn peek_gpu(bytes: *const u8, output: &mut ::core::mem::MaybeUninit<Self>) {
+                    #field_offsets
+                    let output = output.as_mut_ptr();
+                    #(<#tys as #gpu>::peek_gpu(
+                        bytes.add(LAYOUT.0[#indices]),
+                        peek_poke::uninit_field(::core::ptr::addr_of_mut!((*output).#members)),
+                    );)*
+                }
+            }
+
+            unsafe impl peek_poke::Poke for #name {
+                const MAX_SIZE: usize = <Self as #gpu>::SIZE;
+
+                #[inline(always)]
+                unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+                    <Self as #gpu>::poke_gpu(self, bytes);
+                    bytes.add(<Self as #gpu>::SIZE)
+                }
+            }
+        };
+    };
+    let peek_impls = quote! {
+        const _: () = {
+            extern crate peek_poke;
+
+            impl peek_poke::Peek for #name {
+                #[inline(always)]
+                unsafe fn peek_from(bytes: *const u8, output: &mut ::core::mem::MaybeUninit<Self>) -> *const u8 {
+                    <Self as #gpu>::peek_gpu(bytes, output);
+                    bytes.add(<Self as #gpu>::SIZE)
+                }
+            }
+
+            impl peek_poke::TryPeek for #name {}
+        };
+    };
+
+    Ok(if derive == Derive::PeekPoke {
+        quote!(#poke_impls #peek_impls)
+    } else {
+        poke_impls
+    })
+}
+
+/// Which of the traits a derive implements. `Peek` requires `Poke`, so a type
+/// deriving `Peek` must also derive or implement `Poke`.
+#[derive(Clone, Copy, PartialEq)]
+enum Derive {
+    PeekPoke,
+    Poke,
+    Peek,
+}
+
+fn peek_poke_derive(s: Structure) -> TokenStream {
+    derive_inner(s, Derive::PeekPoke).unwrap_or_else(|e| e.to_compile_error())
+}
+
+fn poke_derive(s: Structure) -> TokenStream {
+    derive_inner(s, Derive::Poke).unwrap_or_else(|e| e.to_compile_error())
+}
+
+fn peek_derive(s: Structure) -> TokenStream {
+    derive_inner(s, Derive::Peek).unwrap_or_else(|e| e.to_compile_error())
+}
+
+fn peek_copy_derive(s: Structure) -> TokenStream {
+    let check = derive_bound_check(&s, quote!(::core::marker::Copy));
+    let peek = peek_derive(s);
+    quote!(#peek #check)
+}
+
+fn peek_default_derive(s: Structure) -> TokenStream {
+    let check = derive_bound_check(&s, quote!(::core::default::Default));
+    let peek = peek_derive(s);
+    quote!(#peek #check)
+}
+
+/// Fails compilation unless the type implements `bound` whenever its type
+/// parameters do, for the `Peek` derives named after it.
+fn derive_bound_check(s: &Structure, bound: TokenStream) -> TokenStream {
+    let ast = s.ast();
+    let name = &ast.ident;
+    let mut generics = ast.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote_spanned! { name.span() =>
+        const _: () = {
+            fn _check #impl_generics () #where_clause {
+                fn implements<T: ?Sized + #bound>() {}
+                implements::<#name #ty_generics>();
+            }
+        };
+    }
+}
+
+fn derive_inner(mut s: Structure, derive: Derive) -> syn::Result<TokenStream> {
+    let attrs = ContainerAttrs::parse(&s.ast().attrs)?;
+    let field_attrs = FieldAttrs::parse_all(&s)?;
+    if let Some(ref layout) = attrs.layout {
+        return derive_gpu_layout(&s, &attrs, &field_attrs, layout, derive);
+    }
+    if is_struct(&s) {
+        if let Some(ref tag) = attrs.tag {
+            return Err(Error::new(tag.span(), "`tag` is only supported on enums"));
+        }
+    }
+    let tags = derive_tags(&s, &attrs)?;
     s.binding_name(|_, i| Ident::new(&format!("__self_{}", i), Span::call_site()));
+    s.underscore_const(true);
 
-    let max_size_fn = derive_max_size(&s);
-    let poke_into_fn = derive_poke_into(&s);
-    let peek_from_fn = if is_struct(&s) {
-        derive_peek_from_for_struct(&mut s)
+    let max_size_fn = derive_max_size(&s, &tags, &field_attrs);
+    let verbatim_fields = verbatim_fields(&s, &attrs, &field_attrs);
+    let poke_into_fn = derive_poke_into(&s, &tags, &field_attrs, &verbatim_fields);
+    let verbatim = derive_verbatim(&verbatim_fields);
+    let (poke_niche, peek_niche, try_peek_niche) = derive_niche(&s, &tags);
+    let (peek_from_fn, try_peek_from_fn) = if is_struct(&s) {
+        (
+            derive_peek_from_for_struct(&mut s, &field_attrs, &verbatim_fields),
+            derive_try_peek_from_for_struct(&mut s, &field_attrs, &verbatim_fields),
+        )
     } else {
-        derive_peek_from_for_enum(&mut s)
+        (
+            derive_peek_from_for_enum(&mut s, &tags, &field_attrs),
+            derive_try_peek_from_for_enum(&mut s, &tags, &field_attrs),
+        )
     };
 
-    let poke_impl = s.gen_impl(quote! {
+    // Skipped fields and fields serialized by a `with` module needn't
+    // implement the traits, so they're left out of the bounds.
+    let mut wire = s.clone();
+    wire.filter(|bi| {
+        !FieldAttrs::parse(&bi.ast().attrs, &bi.ast().ty)
+            .is_ok_and(|attrs| attrs.skip || attrs.with.is_some())
+    });
+
+    let poke_impl = wire.gen_impl(quote! {
         extern crate peek_poke;
 
         gen unsafe impl peek_poke::Poke for @Self {
             #max_size_fn
+            #verbatim
             #poke_into_fn
+            #poke_niche
         }
     });
 
-    // To implement `fn peek_from` we require that types implement `Default`
-    // trait to create temporary values. This code does the addition all
-    // manually until https://github.com/mystor/synstructure/issues/24 is fixed.
-    let default_trait = syn::parse_str::<TraitBound>("::std::default::Default").unwrap();
+    // This code adds the bounds manually until
+    // https://github.com/mystor/synstructure/issues/24 is fixed.
     let peek_trait = syn::parse_str::<TraitBound>("peek_poke::Peek").unwrap();
+    let try_peek_trait = syn::parse_str::<TraitBound>("peek_poke::TryPeek").unwrap();
 
     let ast = s.ast();
     let name = &ast.ident;
     let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
     let mut where_clause = where_clause.cloned();
-    s.add_trait_bounds(&default_trait, &mut where_clause, AddBounds::Generics);
-    s.add_trait_bounds(&peek_trait, &mut where_clause, AddBounds::Generics);
-
-    let dummy_const: Ident = sanitize_ident(&format!("_DERIVE_peek_poke_Peek_FOR_{}", name));
+    wire.add_trait_bounds(&peek_trait, &mut where_clause, AddBounds::Generics);
+    // Skipped fields without a `default` path are set to `Default::default()`.
+    let defaulted = s
+        .variants()
+        .iter()
+        .zip(&field_attrs)
+        .flat_map(|(vi, attrs)| vi.bindings().iter().zip(attrs))
+        .filter(|(_, attrs)| attrs.skip && attrs.default.is_none())
+        .map(|(bi, _)| &bi.ast().ty);
+    for ty in defaulted {
+        where_clause
+            .get_or_insert_with(|| parse_quote!(where))
+            .predicates
+            .push(parse_quote!(#ty: ::core::default::Default));
+    }
+    let mut try_where_clause = where_clause.clone();
+    wire.add_trait_bounds(&try_peek_trait, &mut try_where_clause, AddBounds::Generics);
 
     let peek_impl = quote! {
-        #[allow(non_upper_case_globals)]
-        const #dummy_const: () = {
+        const _: () = {
             extern crate peek_poke;
 
             impl #impl_generics peek_poke::Peek for #name #ty_generics #where_clause {
                 #peek_from_fn
+                #peek_niche
+            }
+
+            impl #impl_generics peek_poke::TryPeek for #name #ty_generics #try_where_clause {
+                #try_peek_from_fn
+                #try_peek_niche
             }
         };
     };
 
-    quote! {
-        #poke_impl
-        #peek_impl
-    }
+    let budget_check = match attrs.max_size {
+        Some(ref budget) => derive_max_size_budget(&s, &tags, &field_attrs, budget)?,
+        None => quote! {},
+    };
+
+    Ok(match derive {
+        Derive::PeekPoke => quote! {
+            #poke_impl
+            #peek_impl
+            #budget_check
+        },
+        Derive::Poke => quote! {
+            #poke_impl
+            #budget_check
+        },
+        Derive::Peek => peek_impl,
+    })
 }
 
-decl_derive!([PeekPoke] => peek_poke_derive);
+decl_derive!([PeekPoke, attributes(peek_poke)] =>
+    /// Implements `Poke`, `Peek` and `TryPeek`.
+    peek_poke_derive);
+decl_derive!([Poke, attributes(peek_poke)] =>
+    /// Implements `Poke` only, e.g. for types that borrow what they serialize.
+    poke_derive);
+decl_derive!([Peek, attributes(peek_poke)] =>
+    /// Implements `Peek` and `TryPeek` only, for types with a hand-written
+    /// `Poke`.
+    peek_derive);
+decl_derive!([PeekCopy, attributes(peek_poke)] =>
+    /// Implements `Peek` and `TryPeek` only, like `Peek`, for types that are
+    /// also `Copy`.
+    peek_copy_derive);
+decl_derive!([PeekDefault, attributes(peek_poke)] =>
+    /// Implements `Peek` and `TryPeek` only, like `Peek`, for types that are
+    /// also `Default`.
+    peek_default_derive);
@@ -0,0 +1,87 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::{Peek, PeekError, Poke, TryPeek};
+use core::mem::MaybeUninit;
+
+/// An array of `bool`s serialized as bits, eight to a byte, the first in the
+/// least significant bit of the first byte.
+///
+/// Use `#[peek_poke(bits)]` to encode a `[bool; N]` field of a derived type
+/// this way.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BitArray<const N: usize>(pub [bool; N]);
+
+impl<const N: usize> Default for BitArray<N> {
+    fn default() -> Self {
+        BitArray([false; N])
+    }
+}
+
+/// Check that the bits of `byte` above the `len` bits in use are zero, as
+/// `poke_into()` writes them.
+///
+/// This is a no-op unless the `strict` feature is enabled. It's called by the
+/// `TryPeek` impls of `BitArray` and of `#[derive(PeekPoke)]` types with
+/// packed `bool` fields, not by `peek_from()`.
+#[inline(always)]
+pub fn check_bit_padding(byte: u8, len: u32, type_name: &'static str) -> Result<(), PeekError> {
+    if cfg!(feature = "strict") && u32::from(byte) >> len != 0 {
+        return Err(PeekError::InvalidValue { type_name });
+    }
+    Ok(())
+}
+
+unsafe impl<const N: usize> Poke for BitArray<N> {
+    const MAX_SIZE: usize = N.div_ceil(8);
+
+    #[inline]
+    unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+        for (i, chunk) in self.0.chunks(8).enumerate() {
+            let byte = chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (j, &bit)| byte | (bit as u8) << j);
+            *bytes.add(i) = byte;
+        }
+        bytes.add(Self::MAX_SIZE)
+    }
+}
+
+impl<const N: usize> Peek for BitArray<N> {
+    #[inline]
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        let mut bits = [false; N];
+        for (i, bit) in bits.iter_mut().enumerate() {
+            *bit = *bytes.add(i / 8) >> (i % 8) & 1 != 0;
+        }
+        output.write(BitArray(bits));
+        bytes.add(Self::MAX_SIZE)
+    }
+}
+
+impl<const N: usize> TryPeek for BitArray<N> {
+    #[inline]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        if (end as usize - bytes as usize) < Self::MAX_SIZE {
+            return Err(PeekError::UnexpectedEnd);
+        }
+        let used = N % 8;
+        if used != 0 {
+            check_bit_padding(*bytes.add(N / 8), used as u32, "BitArray")?;
+        }
+        Ok(Self::peek_from(bytes, output))
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::{read_verbatim, try_read_verbatim, write_verbatim, Peek, PeekError, Poke, TryPeek};
+use core::mem::{size_of, MaybeUninit};
+
+/// Conversion of primitives between native and a fixed byte order. Swapping
+/// bytes is its own inverse, so the same methods convert back to native.
+pub(crate) trait ByteOrder: Copy {
+    fn to_le(self) -> Self;
+    fn to_be(self) -> Self;
+}
+
+macro_rules! impl_byte_order_for_int {
+    ($($ty:ty)+) => {
+        $(impl ByteOrder for $ty {
+            #[inline(always)]
+            fn to_le(self) -> Self {
+                <$ty>::to_le(self)
+            }
+            #[inline(always)]
+            fn to_be(self) -> Self {
+                <$ty>::to_be(self)
+            }
+        })+
+    };
+}
+
+impl_byte_order_for_int! {
+    i8 i16 i32 i64 isize
+    u8 u16 u32 u64 usize
+}
+
+macro_rules! impl_byte_order_for_float {
+    ($($ty:ty)+) => {
+        $(impl ByteOrder for $ty {
+            #[inline(always)]
+            fn to_le(self) -> Self {
+                <$ty>::from_bits(self.to_bits().to_le())
+            }
+            #[inline(always)]
+            fn to_be(self) -> Self {
+                <$ty>::from_bits(self.to_bits().to_be())
+            }
+        })+
+    };
+}
+
+impl_byte_order_for_float! { f32 f64 }
+
+/// Convert a primitive from native byte order to the byte order it's
+/// serialized in: little-endian with the `little_endian` feature, otherwise
+/// native.
+#[inline(always)]
+pub(crate) fn to_canonical<T: ByteOrder>(v: T) -> T {
+    if cfg!(feature = "little_endian") {
+        v.to_le()
+    } else {
+        v
+    }
+}
+
+/// The inverse of `to_canonical()`.
+#[inline(always)]
+pub(crate) fn from_canonical<T: ByteOrder>(v: T) -> T {
+    if cfg!(feature = "little_endian") {
+        v.to_le()
+    } else {
+        v
+    }
+}
+
+/// A primitive that is always serialized little-endian, regardless of the
+/// host and of the `little_endian` feature.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Le<T>(pub T);
+
+/// A primitive that is always serialized big-endian, regardless of the host
+/// and of the `little_endian` feature.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Be<T>(pub T);
+
+macro_rules! impl_for_wrapper {
+    ($wrapper:ident, $to:ident, $native:expr; $($ty:ty)+) => {
+        $(unsafe impl Poke for $wrapper<$ty> {
+            const MAX_SIZE: usize = size_of::<$ty>();
+            const VERBATIM: bool = $native;
+
+            #[inline(always)]
+            unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+                write_verbatim(self.0.$to(), bytes)
+            }
+        }
+        impl Peek for $wrapper<$ty> {
+            #[inline(always)]
+            unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+                let mut v = MaybeUninit::<$ty>::uninit();
+                let ptr = read_verbatim(bytes, &mut v);
+                output.write($wrapper(v.assume_init().$to()));
+                ptr
+            }
+        }
+        impl TryPeek for $wrapper<$ty> {
+            #[inline(always)]
+            unsafe fn try_peek_from(
+                bytes: *const u8,
+                end: *const u8,
+                output: &mut MaybeUninit<Self>,
+            ) -> Result<*const u8, PeekError> {
+                let mut v = MaybeUninit::<$ty>::uninit();
+                let ptr = try_read_verbatim(bytes, end, &mut v)?;
+                output.write($wrapper(v.assume_init().$to()));
+                Ok(ptr)
+            }
+        })+
+    };
+}
+
+impl_for_wrapper! {
+    Le, to_le, cfg!(target_endian = "little");
+    i8 i16 i32 i64
+    u8 u16 u32 u64
+    f32 f64
+}
+
+impl_for_wrapper! {
+    Be, to_be, cfg!(target_endian = "big");
+    i8 i16 i32 i64
+    u8 u16 u32 u64
+    f32 f64
+}
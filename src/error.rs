@@ -0,0 +1,83 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::{collections::TryReserveError, error::Error, fmt};
+
+/// The reasons fallible deserialization with [`TryPeek`](crate::TryPeek) can
+/// fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PeekError {
+    /// The buffer ended before a complete value was read.
+    UnexpectedEnd,
+    /// An enum tag did not correspond to any variant of `type_name`.
+    InvalidTag { type_name: &'static str, tag: u64 },
+    /// An `Option` tag was neither `0` (`None`) nor `1` (`Some`).
+    InvalidOptionTag(u8),
+    /// The bytes weren't a valid bit pattern for `type_name`, such as a zero
+    /// for a `NonZero` integer or a surrogate for a `char`.
+    InvalidValue { type_name: &'static str },
+}
+
+impl fmt::Display for PeekError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PeekError::UnexpectedEnd => write!(f, "unexpected end of buffer"),
+            PeekError::InvalidTag { type_name, tag } => {
+                write!(f, "invalid enum tag {} while parsing {}", tag, type_name)
+            }
+            PeekError::InvalidOptionTag(tag) => write!(f, "invalid Option tag {}", tag),
+            PeekError::InvalidValue { type_name } => {
+                write!(f, "invalid value while parsing {}", type_name)
+            }
+        }
+    }
+}
+
+impl Error for PeekError {}
+
+/// The reasons fallible serialization into bounded buffers can fail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PokeError {
+    /// The serialized value needed `needed` bytes but the buffer only had
+    /// `available` bytes of space.
+    BufferTooSmall { needed: usize, available: usize },
+    /// Growing the buffer failed.
+    AllocError(TryReserveError),
+}
+
+impl fmt::Display for PokeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PokeError::BufferTooSmall { needed, available } => write!(
+                f,
+                "buffer too small: needed {} bytes, but only {} available",
+                needed, available
+            ),
+            PokeError::AllocError(ref e) => write!(f, "allocation failed: {}", e),
+        }
+    }
+}
+
+impl Error for PokeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            PokeError::AllocError(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<TryReserveError> for PokeError {
+    fn from(e: TryReserveError) -> Self {
+        PokeError::AllocError(e)
+    }
+}
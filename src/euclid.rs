@@ -8,11 +8,13 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::{Peek, Poke};
+use crate::{max, uninit_field, GpuLayout, Layout, LossyF32, Peek, PeekError, Pod, Poke, TryPeek};
+use core::{mem::MaybeUninit, ptr::addr_of_mut};
 use euclid::{Point2D, Rect, SideOffsets2D, Size2D, Transform3D, Vector2D};
 
 unsafe impl<T: Poke, U> Poke for Point2D<T, U> {
     const MAX_SIZE: usize = 2 * T::MAX_SIZE;
+    const VERBATIM: bool = T::VERBATIM;
 
     #[inline(always)]
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
@@ -23,12 +25,26 @@ unsafe impl<T: Poke, U> Poke for Point2D<T, U> {
 }
 impl<T: Peek, U> Peek for Point2D<T, U> {
     #[inline(always)]
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-        let bytes = T::peek_from(bytes, &mut (*output).x);
-        let bytes = T::peek_from(bytes, &mut (*output).y);
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        let output = output.as_mut_ptr();
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).x)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).y)));
         bytes
     }
 }
+impl<T: TryPeek, U> TryPeek for Point2D<T, U> {
+    #[inline(always)]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        let output = output.as_mut_ptr();
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).x)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).y)))?;
+        Ok(bytes)
+    }
+}
 
 unsafe impl<T: Poke, U> Poke for Rect<T, U> {
     const MAX_SIZE: usize = Point2D::<T, U>::MAX_SIZE + Size2D::<T, U>::MAX_SIZE;
@@ -42,12 +58,31 @@ unsafe impl<T: Poke, U> Poke for Rect<T, U> {
 }
 impl<T: Peek, U> Peek for Rect<T, U> {
     #[inline(always)]
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-        let bytes = Point2D::<T, U>::peek_from(bytes, &mut (*output).origin);
-        let bytes = Size2D::<T, U>::peek_from(bytes, &mut (*output).size);
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        let output = output.as_mut_ptr();
+        let bytes = Point2D::<T, U>::peek_from(bytes, uninit_field(addr_of_mut!((*output).origin)));
+        let bytes = Size2D::<T, U>::peek_from(bytes, uninit_field(addr_of_mut!((*output).size)));
         bytes
     }
 }
+impl<T: TryPeek, U> TryPeek for Rect<T, U> {
+    #[inline(always)]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        let output = output.as_mut_ptr();
+        let bytes = Point2D::<T, U>::try_peek_from(
+            bytes,
+            end,
+            uninit_field(addr_of_mut!((*output).origin)),
+        )?;
+        let bytes =
+            Size2D::<T, U>::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).size)))?;
+        Ok(bytes)
+    }
+}
 
 unsafe impl<T: Poke, U> Poke for SideOffsets2D<T, U> {
     const MAX_SIZE: usize = 4 * T::MAX_SIZE;
@@ -63,17 +98,34 @@ unsafe impl<T: Poke, U> Poke for SideOffsets2D<T, U> {
 }
 impl<T: Peek, U> Peek for SideOffsets2D<T, U> {
     #[inline(always)]
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-        let bytes = T::peek_from(bytes, &mut (*output).top);
-        let bytes = T::peek_from(bytes, &mut (*output).right);
-        let bytes = T::peek_from(bytes, &mut (*output).bottom);
-        let bytes = T::peek_from(bytes, &mut (*output).left);
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        let output = output.as_mut_ptr();
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).top)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).right)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).bottom)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).left)));
         bytes
     }
 }
+impl<T: TryPeek, U> TryPeek for SideOffsets2D<T, U> {
+    #[inline(always)]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        let output = output.as_mut_ptr();
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).top)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).right)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).bottom)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).left)))?;
+        Ok(bytes)
+    }
+}
 
 unsafe impl<T: Poke, U> Poke for Size2D<T, U> {
     const MAX_SIZE: usize = 2 * T::MAX_SIZE;
+    const VERBATIM: bool = T::VERBATIM;
 
     #[inline(always)]
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
@@ -84,15 +136,30 @@ unsafe impl<T: Poke, U> Poke for Size2D<T, U> {
 }
 impl<T: Peek, U> Peek for Size2D<T, U> {
     #[inline(always)]
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-        let bytes = T::peek_from(bytes, &mut (*output).width);
-        let bytes = T::peek_from(bytes, &mut (*output).height);
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        let output = output.as_mut_ptr();
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).width)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).height)));
         bytes
     }
 }
+impl<T: TryPeek, U> TryPeek for Size2D<T, U> {
+    #[inline(always)]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        let output = output.as_mut_ptr();
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).width)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).height)))?;
+        Ok(bytes)
+    }
+}
 
 unsafe impl<T: Poke, S, D> Poke for Transform3D<T, S, D> {
     const MAX_SIZE: usize = 16 * T::MAX_SIZE;
+    const VERBATIM: bool = T::VERBATIM;
 
     #[inline(always)]
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
@@ -117,29 +184,58 @@ unsafe impl<T: Poke, S, D> Poke for Transform3D<T, S, D> {
 }
 impl<T: Peek, S, D> Peek for Transform3D<T, S, D> {
     #[inline(always)]
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-        let bytes = T::peek_from(bytes, &mut (*output).m11);
-        let bytes = T::peek_from(bytes, &mut (*output).m12);
-        let bytes = T::peek_from(bytes, &mut (*output).m13);
-        let bytes = T::peek_from(bytes, &mut (*output).m14);
-        let bytes = T::peek_from(bytes, &mut (*output).m21);
-        let bytes = T::peek_from(bytes, &mut (*output).m22);
-        let bytes = T::peek_from(bytes, &mut (*output).m23);
-        let bytes = T::peek_from(bytes, &mut (*output).m24);
-        let bytes = T::peek_from(bytes, &mut (*output).m31);
-        let bytes = T::peek_from(bytes, &mut (*output).m32);
-        let bytes = T::peek_from(bytes, &mut (*output).m33);
-        let bytes = T::peek_from(bytes, &mut (*output).m34);
-        let bytes = T::peek_from(bytes, &mut (*output).m41);
-        let bytes = T::peek_from(bytes, &mut (*output).m42);
-        let bytes = T::peek_from(bytes, &mut (*output).m43);
-        let bytes = T::peek_from(bytes, &mut (*output).m44);
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        let output = output.as_mut_ptr();
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m11)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m12)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m13)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m14)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m21)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m22)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m23)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m24)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m31)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m32)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m33)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m34)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m41)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m42)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m43)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).m44)));
         bytes
     }
 }
+impl<T: TryPeek, S, D> TryPeek for Transform3D<T, S, D> {
+    #[inline(always)]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        let output = output.as_mut_ptr();
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m11)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m12)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m13)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m14)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m21)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m22)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m23)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m24)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m31)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m32)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m33)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m34)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m41)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m42)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m43)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).m44)))?;
+        Ok(bytes)
+    }
+}
 
 unsafe impl<T: Poke, U> Poke for Vector2D<T, U> {
     const MAX_SIZE: usize = 2 * T::MAX_SIZE;
+    const VERBATIM: bool = T::VERBATIM;
 
     #[inline(always)]
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
@@ -150,9 +246,140 @@ unsafe impl<T: Poke, U> Poke for Vector2D<T, U> {
 }
 impl<T: Peek, U> Peek for Vector2D<T, U> {
     #[inline(always)]
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-        let bytes = T::peek_from(bytes, &mut (*output).x);
-        let bytes = T::peek_from(bytes, &mut (*output).y);
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        let output = output.as_mut_ptr();
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).x)));
+        let bytes = T::peek_from(bytes, uninit_field(addr_of_mut!((*output).y)));
         bytes
     }
 }
+impl<T: TryPeek, U> TryPeek for Vector2D<T, U> {
+    #[inline(always)]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        let output = output.as_mut_ptr();
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).x)))?;
+        let bytes = T::try_peek_from(bytes, end, uninit_field(addr_of_mut!((*output).y)))?;
+        Ok(bytes)
+    }
+}
+
+macro_rules! impl_gpu_layout_for_vec2 {
+    ($($ty:ident { $x:ident, $y:ident })+) => {
+        $(/// A `vec2`.
+        unsafe impl<T: GpuLayout<L>, U, L: Layout> GpuLayout<L> for $ty<T, U> {
+            const ALIGN: usize = 2 * T::SIZE;
+            const SIZE: usize = 2 * T::SIZE;
+
+            #[inline(always)]
+            unsafe fn poke_gpu(&self, bytes: *mut u8) {
+                self.$x.poke_gpu(bytes);
+                self.$y.poke_gpu(bytes.add(T::SIZE));
+            }
+
+            #[inline(always)]
+            unsafe fn peek_gpu(bytes: *const u8, output: &mut MaybeUninit<Self>) {
+                let output = output.as_mut_ptr();
+                T::peek_gpu(bytes, uninit_field(addr_of_mut!((*output).$x)));
+                T::peek_gpu(bytes.add(T::SIZE), uninit_field(addr_of_mut!((*output).$y)));
+            }
+        })+
+    };
+}
+
+impl_gpu_layout_for_vec2! {
+    Point2D { x, y }
+    Size2D { width, height }
+    Vector2D { x, y }
+}
+
+/// A `mat4`, i.e. an array of four `vec4`s, one per row of the transform.
+unsafe impl<T: GpuLayout<L>, S, D, L: Layout> GpuLayout<L> for Transform3D<T, S, D> {
+    const ALIGN: usize = max(4 * T::SIZE, L::MIN_AGGREGATE_ALIGN);
+    const SIZE: usize = 4 * (4 * T::SIZE).next_multiple_of(Self::ALIGN);
+
+    #[inline(always)]
+    unsafe fn poke_gpu(&self, bytes: *mut u8) {
+        let stride = (4 * T::SIZE).next_multiple_of(Self::ALIGN);
+        let rows = [
+            [&self.m11, &self.m12, &self.m13, &self.m14],
+            [&self.m21, &self.m22, &self.m23, &self.m24],
+            [&self.m31, &self.m32, &self.m33, &self.m34],
+            [&self.m41, &self.m42, &self.m43, &self.m44],
+        ];
+        bytes.write_bytes(0, Self::SIZE);
+        for (i, row) in rows.iter().enumerate() {
+            for (j, v) in row.iter().enumerate() {
+                v.poke_gpu(bytes.add(i * stride + j * T::SIZE));
+            }
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn peek_gpu(bytes: *const u8, output: &mut MaybeUninit<Self>) {
+        let stride = (4 * T::SIZE).next_multiple_of(Self::ALIGN);
+        let output = output.as_mut_ptr();
+        let rows = [
+            [
+                addr_of_mut!((*output).m11),
+                addr_of_mut!((*output).m12),
+                addr_of_mut!((*output).m13),
+                addr_of_mut!((*output).m14),
+            ],
+            [
+                addr_of_mut!((*output).m21),
+                addr_of_mut!((*output).m22),
+                addr_of_mut!((*output).m23),
+                addr_of_mut!((*output).m24),
+            ],
+            [
+                addr_of_mut!((*output).m31),
+                addr_of_mut!((*output).m32),
+                addr_of_mut!((*output).m33),
+                addr_of_mut!((*output).m34),
+            ],
+            [
+                addr_of_mut!((*output).m41),
+                addr_of_mut!((*output).m42),
+                addr_of_mut!((*output).m43),
+                addr_of_mut!((*output).m44),
+            ],
+        ];
+        for (i, row) in rows.iter().enumerate() {
+            for (j, &v) in row.iter().enumerate() {
+                T::peek_gpu(bytes.add(i * stride + j * T::SIZE), uninit_field(v));
+            }
+        }
+    }
+}
+
+// These are `#[repr(C)]`, and serialized field by field in declaration order,
+// so they're verbatim if `T` is.
+unsafe impl<T: Pod, U> Pod for Point2D<T, U> {}
+unsafe impl<T: Pod, U> Pod for Size2D<T, U> {}
+unsafe impl<T: Pod, U> Pod for Vector2D<T, U> {}
+unsafe impl<T: Pod, S, D> Pod for Transform3D<T, S, D> {}
+
+// Since 0.20.14 all of these are `#[repr(C)]`, so replacing `T` with an
+// encoding of the same layout keeps their layout.
+unsafe impl<T: LossyF32<E>, E, U> LossyF32<E> for Point2D<T, U> {
+    type Encoded = Point2D<T::Encoded, U>;
+}
+unsafe impl<T: LossyF32<E>, E, U> LossyF32<E> for Size2D<T, U> {
+    type Encoded = Size2D<T::Encoded, U>;
+}
+unsafe impl<T: LossyF32<E>, E, U> LossyF32<E> for Vector2D<T, U> {
+    type Encoded = Vector2D<T::Encoded, U>;
+}
+unsafe impl<T: LossyF32<E>, E, U> LossyF32<E> for Rect<T, U> {
+    type Encoded = Rect<T::Encoded, U>;
+}
+unsafe impl<T: LossyF32<E>, E, U> LossyF32<E> for SideOffsets2D<T, U> {
+    type Encoded = SideOffsets2D<T::Encoded, U>;
+}
+unsafe impl<T: LossyF32<E>, E, S, D> LossyF32<E> for Transform3D<T, S, D> {
+    type Encoded = Transform3D<T::Encoded, S, D>;
+}
@@ -0,0 +1,235 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::{max, peek_from_uninit, uninit_array, uninit_field, Peek, Poke, TryPeek};
+use core::{
+    marker::PhantomData,
+    mem::{size_of, MaybeUninit},
+    ptr::addr_of_mut,
+};
+
+/// The rules of a GLSL buffer layout: [`Std140`] or [`Std430`].
+pub trait Layout {
+    /// The minimum alignment of arrays and structs, which is also the minimum
+    /// stride of array elements.
+    const MIN_AGGREGATE_ALIGN: usize;
+}
+
+/// The std140 layout of uniform buffers, which aligns arrays, their elements
+/// and structs to at least 16 bytes, like a `vec4`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Std140 {}
+
+/// The std430 layout of storage buffers, which aligns arrays and structs to
+/// their most aligned member.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Std430 {}
+
+impl Layout for Std140 {
+    const MIN_AGGREGATE_ALIGN: usize = 16;
+}
+
+impl Layout for Std430 {
+    const MIN_AGGREGATE_ALIGN: usize = 1;
+}
+
+/// A trait for values that can be serialized in the GPU buffer layout `L`,
+/// where each value is placed at an offset that's a multiple of its
+/// alignment, and padding is zeroed.
+///
+/// `#[derive(PeekPoke)]` implements it for structs with a
+/// `#[peek_poke(layout = std140)]` or `#[peek_poke(layout = std430)]`
+/// attribute, whose fields must all implement it.
+///
+/// # Safety
+///
+/// Implementors must ensure that `poke_gpu()` writes, and `peek_gpu()`
+/// reads, no more than `SIZE` bytes.
+pub unsafe trait GpuLayout<L: Layout>: Sized {
+    /// The alignment of the offset of `Self` within a struct or array.
+    const ALIGN: usize;
+
+    /// The size of `Self`, including any padding at the end.
+    const SIZE: usize;
+
+    /// Serialize into the `SIZE` bytes pointed to by `bytes`.
+    ///
+    /// # Safety
+    ///
+    /// `bytes` must point to at least `SIZE` writable bytes.
+    unsafe fn poke_gpu(&self, bytes: *mut u8);
+
+    /// Deserialize from the `SIZE` bytes pointed to by `bytes` into `output`,
+    /// which is initialized on return.
+    ///
+    /// # Safety
+    ///
+    /// `bytes` must point to at least `SIZE` readable bytes.
+    unsafe fn peek_gpu(bytes: *const u8, output: &mut MaybeUninit<Self>);
+}
+
+/// Helper to lay out the fields of a struct, given the `(ALIGN, SIZE)` of
+/// each, as `#[derive(PeekPoke)]` does. Returns the offset of each field and
+/// the offset of the end of the last one.
+pub const fn field_offsets<const N: usize>(fields: [(usize, usize); N]) -> ([usize; N], usize) {
+    let mut offsets = [0; N];
+    let mut end: usize = 0;
+    let mut i = 0;
+    while i < N {
+        offsets[i] = end.next_multiple_of(fields[i].0);
+        end = offsets[i] + fields[i].1;
+        i += 1;
+    }
+    (offsets, end)
+}
+
+macro_rules! impl_for_scalar {
+    ($($ty:ty)+) => {
+        $(unsafe impl<L: Layout> GpuLayout<L> for $ty {
+            const ALIGN: usize = size_of::<$ty>();
+            const SIZE: usize = size_of::<$ty>();
+
+            #[inline(always)]
+            unsafe fn poke_gpu(&self, bytes: *mut u8) {
+                self.poke_into(bytes);
+            }
+
+            #[inline(always)]
+            unsafe fn peek_gpu(bytes: *const u8, output: &mut MaybeUninit<Self>) {
+                <$ty>::peek_from(bytes, output);
+            }
+        })+
+    };
+}
+
+impl_for_scalar! { i32 u32 f32 f64 }
+
+/// A GLSL `bool` is 4 bytes.
+unsafe impl<L: Layout> GpuLayout<L> for bool {
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+
+    #[inline(always)]
+    unsafe fn poke_gpu(&self, bytes: *mut u8) {
+        u32::from(*self).poke_into(bytes);
+    }
+
+    #[inline(always)]
+    unsafe fn peek_gpu(bytes: *const u8, output: &mut MaybeUninit<Self>) {
+        let (int_bool, _) = peek_from_uninit::<u32>(bytes);
+        output.write(int_bool != 0);
+    }
+}
+
+/// A GLSL vector of `N` scalars, e.g. a `vec3` for `GpuVec<f32, 3>`.
+///
+/// Unlike an array, a vector is aligned to twice the size of its scalars
+/// if `N` is 2, and four times otherwise, and is never padded.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuVec<T, const N: usize>(pub [T; N]);
+
+impl<T: Copy + Default, const N: usize> Default for GpuVec<T, N> {
+    fn default() -> Self {
+        GpuVec([T::default(); N])
+    }
+}
+
+macro_rules! impl_for_vec {
+    ($($len:literal: $align:literal)+) => {
+        $(unsafe impl<T: GpuLayout<L>, L: Layout> GpuLayout<L> for GpuVec<T, $len> {
+            const ALIGN: usize = $align * T::SIZE;
+            const SIZE: usize = $len * T::SIZE;
+
+            #[inline]
+            unsafe fn poke_gpu(&self, bytes: *mut u8) {
+                for (i, v) in self.0.iter().enumerate() {
+                    v.poke_gpu(bytes.add(i * T::SIZE));
+                }
+            }
+
+            #[inline]
+            unsafe fn peek_gpu(bytes: *const u8, output: &mut MaybeUninit<Self>) {
+                let output = output.as_mut_ptr() as *mut MaybeUninit<[T; $len]>;
+                for (i, v) in uninit_array(&mut *output).iter_mut().enumerate() {
+                    T::peek_gpu(bytes.add(i * T::SIZE), v);
+                }
+            }
+        })+
+    };
+}
+
+impl_for_vec! { 2: 2 3: 4 4: 4 }
+
+/// Arrays are aligned like their elements, and each element is padded to a
+/// multiple of that alignment, both rounded up to `MIN_AGGREGATE_ALIGN`.
+unsafe impl<T: GpuLayout<L>, L: Layout, const N: usize> GpuLayout<L> for [T; N] {
+    const ALIGN: usize = max(T::ALIGN, L::MIN_AGGREGATE_ALIGN);
+    const SIZE: usize = N * T::SIZE.next_multiple_of(Self::ALIGN);
+
+    #[inline]
+    unsafe fn poke_gpu(&self, bytes: *mut u8) {
+        let stride = T::SIZE.next_multiple_of(Self::ALIGN);
+        bytes.write_bytes(0, Self::SIZE);
+        for (i, v) in self.iter().enumerate() {
+            v.poke_gpu(bytes.add(i * stride));
+        }
+    }
+
+    #[inline]
+    unsafe fn peek_gpu(bytes: *const u8, output: &mut MaybeUninit<Self>) {
+        let stride = T::SIZE.next_multiple_of(Self::ALIGN);
+        for (i, v) in uninit_array(output).iter_mut().enumerate() {
+            T::peek_gpu(bytes.add(i * stride), v);
+        }
+    }
+}
+
+/// A `T` serialized in the GPU buffer layout `L` instead of densely, so that
+/// it can be copied into a uniform or storage buffer as is.
+///
+/// Offsets are relative to the start of the `T`, which must be copied to an
+/// offset in the GPU buffer that's a multiple of `T::ALIGN`.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aligned<T, L>(pub T, PhantomData<L>);
+
+impl<T, L> Aligned<T, L> {
+    /// Wrap `v`, which `Aligned` can't be constructed with directly.
+    pub const fn new(v: T) -> Self {
+        Aligned(v, PhantomData)
+    }
+}
+
+impl<T: Default, L> Default for Aligned<T, L> {
+    fn default() -> Self {
+        Aligned::new(T::default())
+    }
+}
+
+unsafe impl<T: GpuLayout<L>, L: Layout> Poke for Aligned<T, L> {
+    const MAX_SIZE: usize = T::SIZE;
+
+    #[inline(always)]
+    unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+        self.0.poke_gpu(bytes);
+        bytes.add(T::SIZE)
+    }
+}
+
+impl<T: GpuLayout<L>, L: Layout> Peek for Aligned<T, L> {
+    #[inline(always)]
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        T::peek_gpu(bytes, uninit_field(addr_of_mut!((*output.as_mut_ptr()).0)));
+        bytes.add(T::SIZE)
+    }
+}
+
+impl<T: GpuLayout<L>, L: Layout> TryPeek for Aligned<T, L> {}
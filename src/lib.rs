@@ -14,23 +14,217 @@
 //!
 //! ## Binary Encoding Scheme
 //!
+//! Primitives are serialized in native byte order, or little-endian with the
+//! `little_endian` feature. Enum and `Option` tags are primitives, so follow
+//! the same order. Fields of type [`Le<T>`](Le) and [`Be<T>`](Be) are always
+//! little- and big-endian respectively.
+//!
+//! `usize` and `isize` are serialized with the target's pointer width, or as
+//! 64-bit integers with the `fixed_usize` feature. Decoding a value that
+//! doesn't fit on a 32-bit target then panics in `peek_from()` and is an
+//! error from `try_peek_from()`.
+//!
+//! Enums are serialized as a tag followed by the fields of the variant. The
+//! tag is the variant's discriminant, so is its index unless set explicitly
+//! with `A = 5`, and its type is the integer type in `#[repr(...)]`, else the
+//! smallest integer type that can hold the discriminants. Use
+//! `#[peek_poke(tag = ...)]` and explicit discriminants to keep the format
+//! stable as variants are added or reordered.
+//!
+//! `Option<T>` is serialized as a `u8` tag of 0 or 1 followed by the `T` if
+//! it's `Some`, unless `T` has a niche, a value it's never serialized as,
+//! which then encodes `None` without a tag. `NonZero*` types use zero, and
+//! enums deriving `PeekPoke` use the largest value of their tag type unless a
+//! variant's tag already has it, or there's an `other` variant.
+//!
+//! Consecutive `bool` fields of derived types are packed into bytes, eight
+//! to a byte, the first in the least significant bit. [`BitArray<N>`](BitArray)
+//! does the same for `[bool; N]`.
+//!
+//! `peek_from()` reads any nonzero `bool` byte or bit as `true`. With the
+//! `strict` feature, `try_peek_from()` instead rejects `bool` bytes other
+//! than `0` and `1`, and unused bits that are set in packed `bool`s. Only
+//! `TryPeek` is affected, as `peek_from()` trusts its input.
+//!
+//! Structs with a `layout` attribute are instead serialized in a GLSL buffer
+//! layout, std140 or std430, where each field is aligned and padding is
+//! zeroed, so that the bytes can be copied into a GPU buffer as is. See
+//! [`GpuLayout`], and [`Aligned`] to embed such a value in another type.
+//!
 //! ## Usage
 //!
+//! `#[derive(PeekPoke)]` implements [`Poke`], [`Peek`] and [`TryPeek`] for a
+//! struct or enum. `#[derive(Poke)]` implements only `Poke`, for types that
+//! are only ever serialized, such as ones holding a `&'a T`.
+//! `#[derive(Peek)]` implements only `Peek` and `TryPeek`, for types with a
+//! hand-written `Poke`, which `Peek` requires for `MAX_SIZE`.
+//! `#[derive(PeekCopy)]` and `#[derive(PeekDefault)]` do the same, and also
+//! check that the type is `Copy` or `Default`.
+//!
+//! ```
+//! use peek_poke::{Poke, PeekPoke};
+//!
+//! #[derive(PeekPoke)]
+//! struct Point {
+//!     x: f32,
+//!     y: f32,
+//! }
+//!
+//! #[derive(Poke)]
+//! struct Line<'a> {
+//!     from: &'a Point,
+//!     to: &'a Point,
+//! }
+//!
+//! let (a, b) = (Point { x: 0.0, y: 1.0 }, Point { x: 2.0, y: 3.0 });
+//! let mut bytes = Vec::new();
+//! peek_poke::poke_into_vec(&Line { from: &a, to: &b }, &mut bytes);
+//! assert_eq!(bytes.len(), 16);
+//! ```
+//!
+//! ## Derive attributes
+//!
+//! The derives accept `#[peek_poke(...)]` attributes.
+//!
+//! On the struct or enum:
+//!
+//! - `max_size = N`: fail compilation if `MAX_SIZE` exceeds `N` bytes. The
+//!   errors show the total, at the attribute, and the `MAX_SIZE` of each
+//!   field, at its type. Not supported on generic types.
+//! - `tag = u16`: the integer type of enum tags, overriding `#[repr(...)]`.
+//! - `layout = std140` or `layout = std430`: serialize a struct in that GPU
+//!   buffer layout. Its fields must implement [`GpuLayout`], e.g. `f32`,
+//!   [`GpuVec`], arrays and other structs with the same layout, and can't
+//!   have field attributes. Not supported on generic types. `GpuLayout` is
+//!   implemented by `#[derive(Poke)]`, so `#[derive(Peek)]` rejects it.
+//!
+//! ```compile_fail
+//! use peek_poke::PeekPoke;
+//!
+//! #[derive(PeekPoke)]
+//! #[peek_poke(max_size = 8)]
+//! struct Item {
+//!     a: u32,
+//!     b: u64, // error: expected an array with a size of 0, found one with a size of 8
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use peek_poke::{Peek, Poke};
+//!
+//! #[derive(Poke, Peek)]
+//! #[peek_poke(layout = std140)] // error: `layout` requires `#[derive(PeekPoke)]` or ...
+//! struct Light {
+//!     intensity: f32,
+//! }
+//! ```
+//!
+//! On enum variants:
+//!
+//! - `other`: decode tags that don't belong to any variant as this variant,
+//!   instead of panicking in `peek_from()` or returning an error from
+//!   `try_peek_from()`, so that older readers can handle variants added by
+//!   newer writers. The variant is either a unit variant, or has a single
+//!   field of the tag type that holds the unknown tag and is written back in
+//!   its place. Only the tag is read, so the fields of unknown variants
+//!   aren't skipped.
+//!
+//! On fields:
+//!
+//! - `varint`: serialize an unsigned integer as a [`Varint`].
+//! - `zigzag`: serialize a signed integer as a [`ZigZag`].
+//! - `bits`: pack a `bool` with adjacent bit fields, for when its type isn't
+//!   spelled `bool`, e.g. an alias, or serialize a `[bool; N]` as a
+//!   [`BitArray`].
+//! - `f16`: serialize an `f32` as an [`F16`].
+//! - `fixed = I, scale = N`: serialize an `f32` as a [`Fixed<I, N>`](Fixed).
+//! - `skip`: don't serialize the field, e.g. a cache, and set it to
+//!   `Default::default()` when deserializing. Its type needn't implement
+//!   `Poke` or `Peek`.
+//! - `default = "path"`: with `skip`, set the field to the result of calling
+//!   the function `path` instead.
+//! - `with = "module"`: serialize the field with the items of `module`
+//!   instead, e.g. for a foreign type that can't implement `Poke`. For a
+//!   field of type `T`, these are:
+//!
+//!   ```ignore
+//!   pub const MAX_SIZE: usize = ...;
+//!   pub unsafe fn poke_into(value: &T, bytes: *mut u8) -> *mut u8 { ... }
+//!   pub unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<T>) -> *const u8 { ... }
+//!   ```
+//!
+//!   with the same contracts as [`Poke::poke_into()`] and [`Peek::peek_from()`].
+//!   The derived `TryPeek` checks the length of the buffer with
+//!   [`try_peek_with()`], but trusts the contents.
+//!
+//! The float encodings apply to each `f32` in the field's type, so also to a
+//! `Point2D<f32>` or `[f32; 2]`. The type must implement [`LossyF32`], which
+//! guarantees that it's laid out the same when its `f32`s are replaced by
+//! the wrapper.
+//!
+//! ```
+//! use peek_poke::PeekPoke;
+//!
+//! #[derive(PeekPoke)]
+//! struct Item {
+//!     #[peek_poke(varint)]
+//!     id: u64,
+//!     #[peek_poke(zigzag)]
+//!     offset: i32,
+//! }
+//!
+//! let mut bytes = Vec::new();
+//! peek_poke::poke_into_vec(&Item { id: 1, offset: -1 }, &mut bytes);
+//! assert_eq!(bytes, [1, 1]);
+//! ```
+//!
 //! ## Comparison to bincode
 
 #[cfg(feature = "derive")]
 pub use peek_poke_derive::*;
 
-use crate::{slice_ext::*, vec_ext::*};
-use core::{marker::PhantomData, mem::size_of, slice};
+pub use crate::{
+    bits::{check_bit_padding, BitArray},
+    endian::{Be, Le},
+    error::{PeekError, PokeError},
+    gpu::{field_offsets, Aligned, GpuLayout, GpuVec, Layout, Std140, Std430},
+    lossy::{Fixed, LossyF32, F16},
+    pod::{PeekRef, Pod},
+    reader::PeekReader,
+    varint::{Varint, ZigZag},
+    writer::PokeWriter,
+};
+
+use crate::{
+    endian::{from_canonical, to_canonical, ByteOrder},
+    slice_ext::*,
+    vec_ext::*,
+};
+use core::{
+    any::type_name,
+    convert::TryFrom,
+    marker::PhantomData,
+    mem::{self, size_of, MaybeUninit},
+    num::{
+        NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU16, NonZeroU32,
+        NonZeroU64, NonZeroU8, NonZeroUsize,
+    },
+    ptr, slice,
+};
 
+mod bits;
+mod endian;
+mod error;
+mod gpu;
+mod lossy;
+mod pod;
+mod reader;
 mod slice_ext;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod varint;
 mod vec_ext;
-
-union MaybeUninitShim<T: Copy> {
-    uninit: (),
-    init: T,
-}
+mod writer;
 
 /// Helper function that implements `max` as a `const fn` for use in
 /// implementing `const MAX_SIZE` for enums.
@@ -38,8 +232,82 @@ pub const fn max(a: usize, b: usize) -> usize {
     [a, b][(a < b) as usize]
 }
 
-/// Peek helper for constructing a `T` by `Copy`ing into an uninitialized stack
-/// allocation.
+/// Panic if `T::poke_into()` or `T::peek_from()`, named by `op`, wrote or read
+/// more than `T::MAX_SIZE` bytes between `start` and `end`.
+///
+/// This is a no-op unless `debug_assertions` or the `check_max_size` feature
+/// are enabled. It's called by the helpers in this crate and by
+/// `#[derive(PeekPoke)]`, and can be used in hand-written implementations.
+#[inline(always)]
+pub fn check_max_size<T: Poke + ?Sized>(op: &str, start: *const u8, end: *const u8) {
+    if cfg!(any(debug_assertions, feature = "check_max_size")) {
+        let len = end as usize - start as usize;
+        if len > T::MAX_SIZE {
+            panic!(
+                "WRDL: {}::{}() used {} bytes, but MAX_SIZE is {}",
+                core::any::type_name::<T>(),
+                op,
+                len,
+                T::MAX_SIZE
+            );
+        }
+    }
+}
+
+#[inline(always)]
+unsafe fn checked_poke_into<T: Poke>(src: &T, bytes: *mut u8) -> *mut u8 {
+    let end_ptr = src.poke_into(bytes);
+    check_max_size::<T>("poke_into", bytes, end_ptr);
+    end_ptr
+}
+
+#[inline(always)]
+unsafe fn checked_peek_from<T: Peek>(bytes: *const u8, output: &mut MaybeUninit<T>) -> *const u8 {
+    let end_ptr = T::peek_from(bytes, output);
+    check_max_size::<T>("peek_from", bytes, end_ptr);
+    end_ptr
+}
+
+#[inline(always)]
+unsafe fn checked_try_peek_from<T: TryPeek>(
+    bytes: *const u8,
+    end: *const u8,
+    output: &mut MaybeUninit<T>,
+) -> Result<*const u8, PeekError> {
+    let end_ptr = T::try_peek_from(bytes, end, output)?;
+    check_max_size::<T>("try_peek_from", bytes, end_ptr);
+    Ok(end_ptr)
+}
+
+/// Helper to view a field of a `MaybeUninit`, given a pointer to it made
+/// with `ptr::addr_of_mut!()`, so that the field can be deserialized in
+/// place. `#[derive(PeekPoke)]` uses it to deserialize structs.
+///
+/// ```
+/// use peek_poke::Peek;
+/// use std::{mem::MaybeUninit, ptr};
+///
+/// struct Bar {
+///     a: u32,
+///     b: u8,
+/// }
+///
+/// unsafe fn peek_bar(bytes: *const u8, output: &mut MaybeUninit<Bar>) -> *const u8 {
+///     let out = output.as_mut_ptr();
+///     let bytes = u32::peek_from(bytes, peek_poke::uninit_field(ptr::addr_of_mut!((*out).a)));
+///     u8::peek_from(bytes, peek_poke::uninit_field(ptr::addr_of_mut!((*out).b)))
+/// }
+/// ```
+///
+/// # Safety
+///
+/// `field` must be valid for writes for the lifetime `'a`, and not be aliased.
+#[inline(always)]
+pub unsafe fn uninit_field<'a, T>(field: *mut T) -> &'a mut MaybeUninit<T> {
+    &mut *(field as *mut MaybeUninit<T>)
+}
+
+/// Peek helper for constructing a `T` in an uninitialized stack allocation.
 ///
 /// # Safety
 ///
@@ -49,14 +317,15 @@ pub const fn max(a: usize, b: usize) -> usize {
 /// * `bytes` must denote a valid pointer to a block of memory.
 ///
 /// * `bytes` must point to at least the number of bytes `Poke::MAX_SIZE`.
-pub unsafe fn peek_from_uninit<T: Copy + Peek>(bytes: *const u8) -> (T, *const u8) {
-    let mut val = MaybeUninitShim { uninit: () };
-    let bytes = <T>::peek_from(bytes, &mut val.init);
-    (val.init, bytes)
+#[inline(always)]
+pub unsafe fn peek_from_uninit<T: Peek>(bytes: *const u8) -> (T, *const u8) {
+    let mut val = MaybeUninit::uninit();
+    let bytes = checked_peek_from(bytes, &mut val);
+    (val.assume_init(), bytes)
 }
 
-/// Peek helper for constructing a `T` by `Default` initialized stack
-/// allocation.
+/// Peek helper for constructing a `T`, from before `Peek` decoded into
+/// `MaybeUninit`. The same as [`peek_from_uninit`].
 ///
 /// # Safety
 ///
@@ -66,14 +335,164 @@ pub unsafe fn peek_from_uninit<T: Copy + Peek>(bytes: *const u8) -> (T, *const u
 /// * `bytes` must denote a valid pointer to a block of memory.
 ///
 /// * `bytes` must point to at least the number of bytes `Poke::MAX_SIZE`.
-pub unsafe fn peek_from_default<T: Default + Peek>(bytes: *const u8) -> (T, *const u8) {
-    let mut val = T::default();
-    let bytes = <T>::peek_from(bytes, &mut val);
-    (val, bytes)
+#[inline(always)]
+pub unsafe fn peek_from_default<T: Peek>(bytes: *const u8) -> (T, *const u8) {
+    peek_from_uninit(bytes)
+}
+
+/// Fallible version of [`peek_from_uninit`].
+///
+/// # Safety
+///
+/// This function is unsafe because undefined behavior can result if the
+/// caller does not ensure all of the following:
+///
+/// * `bytes` and `end` must denote the start and end of a valid block of
+///   memory.
+#[inline(always)]
+pub unsafe fn try_peek_from_uninit<T: TryPeek>(
+    bytes: *const u8,
+    end: *const u8,
+) -> Result<(T, *const u8), PeekError> {
+    let mut val = MaybeUninit::uninit();
+    let bytes = checked_try_peek_from(bytes, end, &mut val)?;
+    Ok((val.assume_init(), bytes))
+}
+
+/// Fallible version of [`peek_from_default`].
+///
+/// # Safety
+///
+/// This function is unsafe because undefined behavior can result if the
+/// caller does not ensure all of the following:
+///
+/// * `bytes` and `end` must denote the start and end of a valid block of
+///   memory.
+#[inline(always)]
+pub unsafe fn try_peek_from_default<T: TryPeek>(
+    bytes: *const u8,
+    end: *const u8,
+) -> Result<(T, *const u8), PeekError> {
+    try_peek_from_uninit(bytes, end)
+}
+
+/// Fallible version of `peek`, a deserializer that reads at most `max_size`
+/// bytes from the pointer it's given and returns a pointer past the bytes it
+/// used, such as a `peek_from()` function. This is how the provided
+/// `TryPeek::try_peek_from()` is implemented, and how `#[derive(PeekPoke)]`
+/// calls the `peek_from()` of a `#[peek_poke(with = "module")]` module.
+///
+/// If there are fewer than `max_size` bytes between `bytes` and `end`, `peek`
+/// is given a zero padded copy of them instead, and it's an error if it used
+/// the padding. The contents of the bytes are trusted.
+///
+/// # Safety
+///
+/// This function is unsafe because undefined behavior can result if the
+/// caller does not ensure all of the following:
+///
+/// * `bytes` and `end` must denote the start and end of a valid block of
+///   memory.
+///
+/// * `peek` must not read more than `max_size` bytes.
+#[inline(always)]
+pub unsafe fn try_peek_with(
+    bytes: *const u8,
+    end: *const u8,
+    max_size: usize,
+    peek: impl FnOnce(*const u8) -> *const u8,
+) -> Result<*const u8, PeekError> {
+    let len = end as usize - bytes as usize;
+    if max_size <= len {
+        return Ok(peek(bytes));
+    }
+
+    // Not enough bytes to satisfy the contract of `peek`, so copy what there
+    // is into a zero padded buffer and check how much was consumed.
+    let mut red_zone = vec![0u8; max_size];
+    red_zone[..len].copy_from_slice(slice::from_raw_parts(bytes, len));
+    let end_ptr = peek(red_zone.as_ptr());
+    let read = end_ptr as usize - red_zone.as_ptr() as usize;
+    if read > len {
+        return Err(PeekError::UnexpectedEnd);
+    }
+    Ok(bytes.add(read))
+}
+
+/// Serialize the elements of `src` one after another, with a single copy if
+/// `T` is [`Poke::VERBATIM`].
+///
+/// # Safety
+///
+/// This function is unsafe because undefined behavior can result if the
+/// caller does not ensure all of the following:
+///
+/// * `bytes` must denote a valid pointer to a block of memory.
+///
+/// * `bytes` must point to at least `src.len() * T::MAX_SIZE` bytes.
+#[inline]
+pub unsafe fn poke_slice_into<T: Poke>(src: &[T], bytes: *mut u8) -> *mut u8 {
+    if T::VERBATIM {
+        let len = mem::size_of_val(src);
+        ptr::copy_nonoverlapping(src.as_ptr() as *const u8, bytes, len);
+        bytes.add(len)
+    } else {
+        src.iter().fold(bytes, |bytes, e| e.poke_into(bytes))
+    }
+}
+
+/// Deserialize `dst.len()` elements into `dst`, with a single copy if `T` is
+/// [`Poke::VERBATIM`]. All of `dst` is initialized on return.
+///
+/// # Safety
+///
+/// This function is unsafe because undefined behavior can result if the
+/// caller does not ensure all of the following:
+///
+/// * `bytes` must denote a valid pointer to a block of memory.
+///
+/// * `bytes` must point to at least `dst.len() * T::MAX_SIZE` bytes.
+#[inline]
+pub unsafe fn peek_slice_from<T: Peek>(bytes: *const u8, dst: &mut [MaybeUninit<T>]) -> *const u8 {
+    if T::VERBATIM {
+        let len = mem::size_of_val(dst);
+        ptr::copy_nonoverlapping(bytes, dst.as_mut_ptr() as *mut u8, len);
+        bytes.add(len)
+    } else {
+        dst.iter_mut()
+            .fold(bytes, |bytes, e| T::peek_from(bytes, e))
+    }
+}
+
+/// Fallible version of [`peek_slice_from`]. On error, `dst` may be partially
+/// initialized.
+///
+/// # Safety
+///
+/// This function is unsafe because undefined behavior can result if the
+/// caller does not ensure all of the following:
+///
+/// * `bytes` and `end` must denote the start and end of a valid block of
+///   memory.
+#[inline]
+pub unsafe fn try_peek_slice_from<T: TryPeek>(
+    bytes: *const u8,
+    end: *const u8,
+    dst: &mut [MaybeUninit<T>],
+) -> Result<*const u8, PeekError> {
+    if T::VERBATIM {
+        if (end as usize - bytes as usize) < mem::size_of_val(dst) {
+            return Err(PeekError::UnexpectedEnd);
+        }
+        Ok(peek_slice_from(bytes, dst))
+    } else {
+        dst.iter_mut()
+            .try_fold(bytes, |bytes, e| T::try_peek_from(bytes, end, e))
+    }
 }
 
-/// Peek inplace a `T` from a slice of bytes, returning a slice of the remaining
-/// bytes. `src` must contain at least `T::MAX_SIZE` bytes.
+/// Peek a `T` from a slice of bytes into `dst`, returning a slice of the
+/// remaining bytes. `src` must contain at least `T::MAX_SIZE` bytes.
 ///
 /// [`ensure_red_zone`] can be used to add required padding.
 pub fn peek_from_slice<'a, T: Peek>(src: &'a [u8], dst: &mut T) -> &'a [u8] {
@@ -83,7 +502,8 @@ pub fn peek_from_slice<'a, T: Peek>(src: &'a [u8], dst: &mut T) -> &'a [u8] {
             T::MAX_SIZE < src.len(),
             "WRDL: unexpected end of display list"
         );
-        let end_ptr = T::peek_from(src.as_ptr(), dst);
+        let (val, end_ptr) = peek_from_uninit(src.as_ptr());
+        *dst = val;
         let len = end_ptr as usize - src.as_ptr() as usize;
         // Did someone break the T::peek_from() can't read more than T::MAX_SIZE
         // bytes contract?
@@ -92,6 +512,26 @@ pub fn peek_from_slice<'a, T: Peek>(src: &'a [u8], dst: &mut T) -> &'a [u8] {
     }
 }
 
+/// Fallible version of [`peek_from_slice`] for untrusted input.
+///
+/// Unlike `peek_from_slice`, `src` doesn't require a red zone: truncated input,
+/// invalid enum tags and invalid `Option` tags are reported as a [`PeekError`]
+/// instead of panicking. `dst` is left unchanged on error.
+pub fn try_peek_from_slice<'a, T: TryPeek>(
+    src: &'a [u8],
+    dst: &mut T,
+) -> Result<&'a [u8], PeekError> {
+    unsafe {
+        let start = src.as_ptr();
+        let end = start.add(src.len());
+        let (val, end_ptr) = try_peek_from_uninit(start, end)?;
+        *dst = val;
+        let len = end_ptr as usize - start as usize;
+        assert!(len <= src.len(), "WRDL: TryPeek read past end of buffer");
+        Ok(slice::from_raw_parts(end_ptr, src.len() - len))
+    }
+}
+
 /// Poke helper to insert a serialized version of `src` at the beginning for `dst`.
 pub fn poke_inplace_slice<T: Poke>(src: &T, dst: &mut [u8]) {
     assert!(
@@ -99,16 +539,71 @@ pub fn poke_inplace_slice<T: Poke>(src: &T, dst: &mut [u8]) {
         "WRDL: buffer too small to write into"
     );
     unsafe {
-        src.poke_into(dst.as_mut_ptr());
+        checked_poke_into(src, dst.as_mut_ptr());
     }
 }
 
+/// Fallible version of [`poke_inplace_slice`] for fixed-size buffers.
+///
+/// Returns the number of bytes written to the beginning of `dst`. If `dst` is
+/// shorter than `T::MAX_SIZE`, `src` is serialized into a temporary buffer
+/// first, so `dst` only needs to hold the bytes actually written. The buffer
+/// is on the stack if `T::MAX_SIZE` is at most 256 bytes, otherwise it's
+/// allocated. On error `dst` is left unchanged.
+pub fn try_poke_into_slice<T: Poke>(src: &T, dst: &mut [u8]) -> Result<usize, PokeError> {
+    unsafe { try_poke_into_ptr(src, dst.as_mut_ptr(), dst.len()) }
+}
+
+/// The largest `MAX_SIZE` that `try_poke_into_ptr` serializes into a buffer
+/// on the stack, rather than allocating one.
+const STACK_SCRATCH_SIZE: usize = 256;
+
+/// Poke `src` into the `available` bytes pointed to by `dst`, returning the
+/// number of bytes written. If fewer than `T::MAX_SIZE` bytes are available,
+/// `src` is poked into a scratch buffer and copied, which allocates if
+/// `T::MAX_SIZE` is more than `STACK_SCRATCH_SIZE`.
+unsafe fn try_poke_into_ptr<T: Poke>(
+    src: &T,
+    dst: *mut u8,
+    available: usize,
+) -> Result<usize, PokeError> {
+    if T::MAX_SIZE <= available {
+        let end_ptr = checked_poke_into(src, dst);
+        return Ok(end_ptr as usize - dst as usize);
+    }
+
+    if T::MAX_SIZE <= STACK_SCRATCH_SIZE {
+        let mut scratch = MaybeUninit::<[u8; STACK_SCRATCH_SIZE]>::uninit();
+        return try_poke_via_scratch(src, scratch.as_mut_ptr() as *mut u8, dst, available);
+    }
+    let mut scratch = Vec::new();
+    scratch.try_reserve_exact(T::MAX_SIZE)?;
+    try_poke_via_scratch(src, scratch.as_mut_ptr(), dst, available)
+}
+
+/// Poke `src` into `scratch`, which has room for `T::MAX_SIZE` bytes, then
+/// copy what was written to `dst` if it fits in `available` bytes.
+unsafe fn try_poke_via_scratch<T: Poke>(
+    src: &T,
+    scratch: *mut u8,
+    dst: *mut u8,
+    available: usize,
+) -> Result<usize, PokeError> {
+    let end_ptr = checked_poke_into(src, scratch);
+    let needed = end_ptr as usize - scratch as usize;
+    if needed > available {
+        return Err(PokeError::BufferTooSmall { needed, available });
+    }
+    dst.copy_from_nonoverlapping(scratch, needed);
+    Ok(needed)
+}
+
 /// Poke helper to append a serialized version of `src` to the end of `dst`.
 pub fn poke_into_vec<T: Poke>(src: &T, dst: &mut Vec<u8>) {
     dst.reserve(T::MAX_SIZE);
     unsafe {
         let ptr = dst.as_end_mut_ptr();
-        let end_ptr = src.poke_into(ptr);
+        let end_ptr = checked_poke_into(src, ptr);
         dst.set_end_ptr(end_ptr);
     }
 }
@@ -126,13 +621,81 @@ where
         let ptr = dst.as_end_mut_ptr();
         // Guard against the possibility of a misbehaved implementation of
         // ExactSizeIterator by writing at most `len` items.
-        let end_ptr = src.take(len).fold(ptr, |ptr, item| item.poke_into(ptr));
+        let end_ptr = src
+            .take(len)
+            .fold(ptr, |ptr, item| checked_poke_into(&item, ptr));
         dst.set_end_ptr(end_ptr);
     }
 
     len
 }
 
+/// Fallible version of [`poke_into_vec`] that reports allocation failure
+/// instead of aborting. On error `dst` is left unchanged.
+pub fn try_poke_into_vec<T: Poke>(src: &T, dst: &mut Vec<u8>) -> Result<(), PokeError> {
+    dst.try_reserve(T::MAX_SIZE)?;
+    unsafe {
+        let ptr = dst.as_end_mut_ptr();
+        let end_ptr = checked_poke_into(src, ptr);
+        dst.set_end_ptr(end_ptr);
+    }
+    Ok(())
+}
+
+/// Fallible version of [`poke_extend_vec`] that reports allocation failure
+/// instead of aborting. On error `dst` is left unchanged.
+pub fn try_poke_extend_vec<I>(src: I, dst: &mut Vec<u8>) -> Result<usize, PokeError>
+where
+    I: ExactSizeIterator,
+    I::Item: Poke,
+{
+    let len = src.len();
+    // An overflowing size can never be reserved, so let `try_reserve` report
+    // it as a capacity overflow.
+    let max_size = len.saturating_mul(I::Item::MAX_SIZE);
+    dst.try_reserve(max_size)?;
+    unsafe {
+        let ptr = dst.as_end_mut_ptr();
+        let end_ptr = src
+            .take(len)
+            .fold(ptr, |ptr, item| checked_poke_into(&item, ptr));
+        dst.set_end_ptr(end_ptr);
+    }
+
+    Ok(len)
+}
+
+/// Serialize the elements of `src` onto the end of `dst`, with a single copy
+/// if `T` is [`Poke::VERBATIM`], unlike [`poke_extend_vec`].
+pub fn poke_slice_into_vec<T: Poke>(src: &[T], dst: &mut Vec<u8>) {
+    dst.reserve(src.len() * T::MAX_SIZE);
+    unsafe {
+        let ptr = dst.as_end_mut_ptr();
+        let end_ptr = if T::VERBATIM {
+            poke_slice_into(src, ptr)
+        } else {
+            src.iter().fold(ptr, |ptr, e| checked_poke_into(e, ptr))
+        };
+        dst.set_end_ptr(end_ptr);
+    }
+}
+
+/// Fallible version of [`poke_slice_into_vec`] that reports allocation
+/// failure instead of aborting. On error `dst` is left unchanged.
+pub fn try_poke_slice_into_vec<T: Poke>(src: &[T], dst: &mut Vec<u8>) -> Result<(), PokeError> {
+    dst.try_reserve(src.len().saturating_mul(T::MAX_SIZE))?;
+    unsafe {
+        let ptr = dst.as_end_mut_ptr();
+        let end_ptr = if T::VERBATIM {
+            poke_slice_into(src, ptr)
+        } else {
+            src.iter().fold(ptr, |ptr, e| checked_poke_into(e, ptr))
+        };
+        dst.set_end_ptr(end_ptr);
+    }
+    Ok(())
+}
+
 /// Add `T::MAX_SIZE` "red zone" (padding of zeroes) to the end of the vec of
 /// `bytes`. This allows deserialization to assert that at least `T::MAX_SIZE`
 /// bytes exist at all times.
@@ -145,12 +708,40 @@ pub fn ensure_red_zone<T: Poke>(bytes: &mut Vec<u8>) {
     }
 }
 
+/// Fallible version of [`ensure_red_zone`] that reports allocation failure
+/// instead of aborting. On error `bytes` is left unchanged.
+pub fn try_ensure_red_zone<T: Poke>(bytes: &mut Vec<u8>) -> Result<(), PokeError> {
+    bytes.try_reserve(T::MAX_SIZE)?;
+    unsafe {
+        let end_ptr = bytes.as_end_mut_ptr();
+        end_ptr.write_bytes(0, T::MAX_SIZE);
+        bytes.set_end_ptr(end_ptr.add(T::MAX_SIZE));
+    }
+    Ok(())
+}
+
+/// Whether primitives are serialized in native byte order, which is always
+/// unless the `little_endian` feature is enabled on a big-endian target.
+const NATIVE_ORDER: bool = !cfg!(feature = "little_endian") || cfg!(target_endian = "little");
+
 #[inline]
-unsafe fn read_verbatim<T>(src: *const u8, dst: *mut T) -> *const u8 {
-    *dst = (src as *const T).read_unaligned();
+unsafe fn read_verbatim<T>(src: *const u8, dst: &mut MaybeUninit<T>) -> *const u8 {
+    dst.write((src as *const T).read_unaligned());
     src.add(size_of::<T>())
 }
 
+#[inline]
+unsafe fn try_read_verbatim<T>(
+    src: *const u8,
+    end: *const u8,
+    dst: &mut MaybeUninit<T>,
+) -> Result<*const u8, PeekError> {
+    if (end as usize - src as usize) < size_of::<T>() {
+        return Err(PeekError::UnexpectedEnd);
+    }
+    Ok(read_verbatim(src, dst))
+}
+
 #[inline]
 unsafe fn write_verbatim<T>(src: T, dst: *mut u8) -> *mut u8 {
     (dst as *mut T).write_unaligned(src);
@@ -190,8 +781,12 @@ mod euclid;
 /// ensure that they adhere to these contracts:
 ///
 /// * `MAX_SIZE` query and calculations in general must be correct.  Callers
-///    of this trait are expected to rely on the contract defined on each
-///    method, and implementors must ensure such contracts remain true.
+///   of this trait are expected to rely on the contract defined on each
+///   method, and implementors must ensure such contracts remain true.
+///
+/// * `poke_into()` must initialize every byte between `bytes` and the
+///   pointer it returns. Callers such as [`poke_into_vec`] and
+///   [`PokeWriter`] expose those bytes as initialized `u8`s.
 pub unsafe trait Poke {
     /// Return the maximum number of bytes that the serialized version of `Self`
     /// will occupy.
@@ -206,6 +801,8 @@ pub unsafe trait Poke {
     /// Serialize into the buffer pointed to by `bytes`.
     ///
     /// Returns a pointer to the next byte after the serialized representation of `Self`.
+    /// Implementors guarantee that every byte before it, from `bytes` on, has
+    /// been written, including any padding.
     ///
     /// # Safety
     ///
@@ -216,6 +813,37 @@ pub unsafe trait Poke {
     ///
     /// * `bytes` must point to at least the number of bytes `MAX_SIZE`.
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8;
+
+    /// Whether `Self` is serialized verbatim, as its bytes in memory, so that
+    /// slices and arrays of it can be copied in bulk. That requires `MAX_SIZE`
+    /// to be `size_of::<Self>()`, `Self` to have no padding, and any bytes to
+    /// be a valid `Self` that `peek_from()` and `try_peek_from()` accept.
+    ///
+    /// It's set for the integer and float primitives when they're serialized
+    /// in native byte order, for arrays of verbatim types, and for
+    /// `#[repr(C)]` structs deriving `PeekPoke` whose fields are all verbatim
+    /// and leave no padding.
+    const VERBATIM: bool = false;
+
+    /// Whether `Option<Self>` can be serialized without a tag, because `Self`
+    /// has a niche: a value that a `Self` is never serialized as, which is
+    /// used to encode `None`. It's zero for the `NonZero*` types, and an
+    /// unused tag value for enums deriving `PeekPoke`.
+    ///
+    /// Types that set `NICHE` must also implement `poke_niche_into()`,
+    /// `Peek::peek_niche_from()` and `TryPeek::try_peek_niche_from()`.
+    const NICHE: bool = false;
+
+    /// Serialize the niche, i.e. `None`, into the buffer pointed to by
+    /// `bytes`. Only called if `NICHE` is set.
+    ///
+    /// # Safety
+    ///
+    /// As for `poke_into()`.
+    unsafe fn poke_niche_into(bytes: *mut u8) -> *mut u8 {
+        let _ = bytes;
+        unreachable!("Poke::NICHE is not set")
+    }
 }
 
 /// A trait for values that provide deserialization from buffers of bytes.
@@ -234,10 +862,11 @@ pub unsafe trait Poke {
 /// ...
 ///
 /// impl Peek for Bar {
-///     unsafe fn peek_from(&mut self, bytes: *const u8) -> *const u8 {
-///         let bytes = self.a.peek_from(bytes);
-///         let bytes = self.b.peek_from(bytes);
-///         self.c.peek_from(bytes)
+///     unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+///         let output = output.as_mut_ptr();
+///         let bytes = u32::peek_from(bytes, uninit_field(ptr::addr_of_mut!((*output).a)));
+///         let bytes = u8::peek_from(bytes, uninit_field(ptr::addr_of_mut!((*output).b)));
+///         i16::peek_from(bytes, uninit_field(ptr::addr_of_mut!((*output).c)))
 ///     }
 /// }
 /// ```
@@ -250,8 +879,9 @@ pub unsafe trait Poke {
 /// * Callers of this trait are expected to rely on the contract defined on each
 ///   method, and implementors must ensure that `peek_from()` doesn't read more
 ///   bytes from `bytes` than the value `Peek::MAX_SIZE`.
-pub trait Peek: Poke {
-    /// Deserialize from the buffer pointed to by `bytes`.
+pub trait Peek: Poke + Sized {
+    /// Deserialize from the buffer pointed to by `bytes` into `output`, which
+    /// is initialized on return.
     ///
     /// Returns a pointer to the next byte after the unconsumed bytes not used
     /// to deserialize the representation of `Self`.
@@ -264,7 +894,74 @@ pub trait Peek: Poke {
     /// * `bytes` must denote a valid pointer to a block of memory.
     ///
     /// * `bytes` must point to at least the number of bytes `Poke::MAX_SIZE`.
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8;
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8;
+
+    /// Check whether the buffer pointed to by `bytes` holds the niche, and
+    /// return a pointer to the next byte after it if so. Only called if
+    /// `Poke::NICHE` is set.
+    ///
+    /// # Safety
+    ///
+    /// As for `peek_from()`.
+    unsafe fn peek_niche_from(bytes: *const u8) -> Option<*const u8> {
+        let _ = bytes;
+        unreachable!("Poke::NICHE is not set")
+    }
+}
+
+/// A trait for values that provide fallible deserialization from buffers of
+/// bytes that may be truncated or corrupt, such as buffers received from
+/// another process.
+///
+/// `#[derive(PeekPoke)]` implements `TryPeek` along with `Peek`. Hand-written
+/// `Peek` implementations can opt into the provided `try_peek_from()`, which
+/// checks the length of the buffer but trusts the contents, with an empty
+/// impl:
+///
+/// ```ignore
+/// impl TryPeek for Bar {}
+/// ```
+pub trait TryPeek: Peek {
+    /// Deserialize from the buffer between `bytes` and `end`.
+    ///
+    /// Returns a pointer to the next byte after the bytes used to deserialize
+    /// the representation of `Self`, or a [`PeekError`] if the buffer is too
+    /// short or contains an invalid value. On success, `output` is
+    /// initialized. On error, it may be partially initialized, and must not
+    /// be assumed to be initialized.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because undefined behavior can result if the
+    /// caller does not ensure all of the following:
+    ///
+    /// * `bytes` and `end` must denote the start and end of a valid block of
+    ///   memory.
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        try_peek_with(bytes, end, Self::MAX_SIZE, |bytes| {
+            checked_peek_from(bytes, output)
+        })
+    }
+
+    /// Fallible version of `Peek::peek_niche_from()`. Only called if
+    /// `Poke::NICHE` is set.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because undefined behavior can result if the
+    /// caller does not ensure that `bytes` and `end` denote the start and end
+    /// of a valid block of memory.
+    unsafe fn try_peek_niche_from(
+        bytes: *const u8,
+        end: *const u8,
+    ) -> Result<Option<*const u8>, PeekError> {
+        let _ = (bytes, end);
+        unreachable!("Poke::NICHE is not set")
+    }
 }
 
 macro_rules! impl_poke_for_deref {
@@ -286,27 +983,122 @@ macro_rules! impl_for_primitive {
     ($($ty:ty)+) => {
         $(unsafe impl Poke for $ty {
             const MAX_SIZE: usize = size_of::<Self>();
+            const VERBATIM: bool = NATIVE_ORDER;
 
             #[inline(always)]
             unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
-                write_verbatim(*self, bytes)
+                write_verbatim(to_canonical(*self), bytes)
             }
         }
         impl Peek for $ty {
             #[inline(always)]
-            unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-                read_verbatim(bytes, output)
+            unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+                let ptr = read_verbatim(bytes, output);
+                output.write(from_canonical(output.assume_init()));
+                ptr
+            }
+        }
+        impl TryPeek for $ty {
+            #[inline(always)]
+            unsafe fn try_peek_from(
+                bytes: *const u8,
+                end: *const u8,
+                output: &mut MaybeUninit<Self>,
+            ) -> Result<*const u8, PeekError> {
+                let ptr = try_read_verbatim(bytes, end, output)?;
+                output.write(from_canonical(output.assume_init()));
+                Ok(ptr)
             }
         })+
     };
 }
 
 impl_for_primitive! {
-    i8 i16 i32 i64 isize
-    u8 u16 u32 u64 usize
+    i8 i16 i32 i64
+    u8 u16 u32 u64
     f32 f64
 }
 
+/// The integer types `usize` and `isize` are serialized as. With the
+/// `fixed_usize` feature these are 64-bit, so the encoding doesn't depend on
+/// the pointer width of the target.
+#[cfg(feature = "fixed_usize")]
+type WireUsize = u64;
+#[cfg(feature = "fixed_usize")]
+type WireIsize = i64;
+#[cfg(not(feature = "fixed_usize"))]
+type WireUsize = usize;
+#[cfg(not(feature = "fixed_usize"))]
+type WireIsize = isize;
+
+/// Converts `wire`, a `usize` or `isize` as it's serialized, to `S`. It's an
+/// error if the value doesn't fit, which can happen with the `fixed_usize`
+/// feature on a 32-bit target.
+pub(crate) fn narrow_size<W, S: TryFrom<W>>(wire: W) -> Result<S, PeekError> {
+    S::try_from(wire).map_err(|_| PeekError::InvalidValue {
+        type_name: type_name::<S>(),
+    })
+}
+
+/// Implements `Poke`, `Peek` and `TryPeek` for `usize`, `isize` and their
+/// byte order wrappers. `$order` converts the wire integer to and from the
+/// byte order it's serialized in, which is native if `$native`. Decoding a
+/// value that doesn't fit in the target's `usize` or `isize` is an error.
+macro_rules! impl_for_size {
+    ($($ty:ty: $size:ty as $wire:ty, $order:path, $native:expr, |$s:ident| $get:expr, |$v:ident| $make:expr;)+) => {
+        $(unsafe impl Poke for $ty {
+            const MAX_SIZE: usize = size_of::<$wire>();
+            const VERBATIM: bool = $native && size_of::<$wire>() == size_of::<$size>();
+
+            #[inline(always)]
+            unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+                let $s = self;
+                write_verbatim($order($get as $wire), bytes)
+            }
+        }
+        impl Peek for $ty {
+            #[inline(always)]
+            unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+                let mut wire = MaybeUninit::<$wire>::uninit();
+                let ptr = read_verbatim(bytes, &mut wire);
+                let wire = $order(wire.assume_init());
+                let $v: $size = match narrow_size(wire) {
+                    Ok(v) => v,
+                    Err(_) => panic!(
+                        concat!("WRDL: {} doesn't fit in ", stringify!($size)),
+                        wire
+                    ),
+                };
+                output.write($make);
+                ptr
+            }
+        }
+        impl TryPeek for $ty {
+            #[inline(always)]
+            unsafe fn try_peek_from(
+                bytes: *const u8,
+                end: *const u8,
+                output: &mut MaybeUninit<Self>,
+            ) -> Result<*const u8, PeekError> {
+                let mut wire = MaybeUninit::<$wire>::uninit();
+                let ptr = try_read_verbatim(bytes, end, &mut wire)?;
+                let $v: $size = narrow_size($order(wire.assume_init()))?;
+                output.write($make);
+                Ok(ptr)
+            }
+        })+
+    };
+}
+
+impl_for_size! {
+    usize: usize as WireUsize, to_canonical, NATIVE_ORDER, |s| *s, |v| v;
+    isize: isize as WireIsize, to_canonical, NATIVE_ORDER, |s| *s, |v| v;
+    Le<usize>: usize as WireUsize, ByteOrder::to_le, cfg!(target_endian = "little"), |s| s.0, |v| Le(v);
+    Le<isize>: isize as WireIsize, ByteOrder::to_le, cfg!(target_endian = "little"), |s| s.0, |v| Le(v);
+    Be<usize>: usize as WireUsize, ByteOrder::to_be, cfg!(target_endian = "big"), |s| s.0, |v| Be(v);
+    Be<isize>: isize as WireIsize, ByteOrder::to_be, cfg!(target_endian = "big"), |s| s.0, |v| Be(v);
+}
+
 unsafe impl Poke for bool {
     const MAX_SIZE: usize = u8::MAX_SIZE;
 
@@ -318,16 +1110,141 @@ unsafe impl Poke for bool {
 
 impl Peek for bool {
     #[inline]
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-        let mut int_bool = 0u8;
-        let ptr = <u8>::peek_from(bytes, &mut int_bool);
-        *output = int_bool != 0;
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        let (int_bool, ptr) = peek_from_uninit::<u8>(bytes);
+        output.write(int_bool != 0);
+        ptr
+    }
+}
+
+impl TryPeek for bool {
+    #[inline]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        let (int_bool, ptr) = try_peek_from_uninit::<u8>(bytes, end)?;
+        // `poke_into()` only writes `0` or `1`, so with the `strict` feature
+        // anything else is rejected instead of being read as `true`.
+        if cfg!(feature = "strict") && int_bool > 1 {
+            return Err(PeekError::InvalidValue { type_name: "bool" });
+        }
+        output.write(int_bool != 0);
+        Ok(ptr)
+    }
+}
+
+unsafe impl Poke for char {
+    const MAX_SIZE: usize = u32::MAX_SIZE;
+
+    #[inline]
+    unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+        (*self as u32).poke_into(bytes)
+    }
+}
+
+impl Peek for char {
+    #[inline]
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        let (int_char, ptr) = peek_from_uninit::<u32>(bytes);
+        output.write(core::char::from_u32(int_char).unwrap_or_else(|| {
+            panic!(
+                "WRDL: memory corruption detected while parsing char - {:#x} is not a unicode scalar value",
+                int_char
+            )
+        }));
         ptr
     }
 }
 
+impl TryPeek for char {
+    #[inline]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        let (int_char, ptr) = try_peek_from_uninit::<u32>(bytes, end)?;
+        output.write(
+            core::char::from_u32(int_char).ok_or(PeekError::InvalidValue { type_name: "char" })?,
+        );
+        Ok(ptr)
+    }
+}
+
+macro_rules! impl_for_nonzero {
+    ($($ty:ident: $int:ty)+) => {
+        $(unsafe impl Poke for $ty {
+            const MAX_SIZE: usize = <$int>::MAX_SIZE;
+
+            #[inline(always)]
+            unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+                self.get().poke_into(bytes)
+            }
+
+            const NICHE: bool = true;
+
+            #[inline(always)]
+            unsafe fn poke_niche_into(bytes: *mut u8) -> *mut u8 {
+                (0 as $int).poke_into(bytes)
+            }
+        }
+        impl Peek for $ty {
+            #[inline(always)]
+            unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+                let (int, ptr) = peek_from_uninit::<$int>(bytes);
+                output.write($ty::new(int).unwrap_or_else(|| {
+                    panic!(concat!("WRDL: memory corruption detected while parsing ",
+                        stringify!($ty), " - value was zero"))
+                }));
+                ptr
+            }
+
+            #[inline(always)]
+            unsafe fn peek_niche_from(bytes: *const u8) -> Option<*const u8> {
+                let (int, ptr) = peek_from_uninit::<$int>(bytes);
+                if int == 0 {
+                    Some(ptr)
+                } else {
+                    None
+                }
+            }
+        }
+        impl TryPeek for $ty {
+            #[inline(always)]
+            unsafe fn try_peek_from(
+                bytes: *const u8,
+                end: *const u8,
+                output: &mut MaybeUninit<Self>,
+            ) -> Result<*const u8, PeekError> {
+                let (int, ptr) = try_peek_from_uninit::<$int>(bytes, end)?;
+                output.write($ty::new(int).ok_or(PeekError::InvalidValue {
+                    type_name: stringify!($ty),
+                })?);
+                Ok(ptr)
+            }
+
+            #[inline(always)]
+            unsafe fn try_peek_niche_from(
+                bytes: *const u8,
+                end: *const u8,
+            ) -> Result<Option<*const u8>, PeekError> {
+                let (int, ptr) = try_peek_from_uninit::<$int>(bytes, end)?;
+                Ok(if int == 0 { Some(ptr) } else { None })
+            }
+        })+
+    };
+}
+
+impl_for_nonzero! {
+    NonZeroI8: i8 NonZeroI16: i16 NonZeroI32: i32 NonZeroI64: i64 NonZeroIsize: isize
+    NonZeroU8: u8 NonZeroU16: u16 NonZeroU32: u32 NonZeroU64: u64 NonZeroUsize: usize
+}
+
 unsafe impl<T> Poke for PhantomData<T> {
     const MAX_SIZE: usize = 0;
+    const VERBATIM: bool = true;
 
     #[inline(always)]
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
@@ -337,19 +1254,63 @@ unsafe impl<T> Poke for PhantomData<T> {
 
 impl<T> Peek for PhantomData<T> {
     #[inline(always)]
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-        *output = PhantomData;
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        output.write(PhantomData);
         bytes
     }
 }
 
+impl<T> TryPeek for PhantomData<T> {
+    #[inline(always)]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        _end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        output.write(PhantomData);
+        Ok(bytes)
+    }
+}
+
+/// Decode a `T` into `output` as `Some`.
+#[inline]
+unsafe fn peek_some_from<T: Peek>(
+    bytes: *const u8,
+    output: &mut MaybeUninit<Option<T>>,
+) -> *const u8 {
+    let (val, bytes) = peek_from_uninit(bytes);
+    output.write(Some(val));
+    bytes
+}
+
+/// Fallible version of [`peek_some_from`].
+#[inline]
+unsafe fn try_peek_some_from<T: TryPeek>(
+    bytes: *const u8,
+    end: *const u8,
+    output: &mut MaybeUninit<Option<T>>,
+) -> Result<*const u8, PeekError> {
+    let (val, bytes) = try_peek_from_uninit(bytes, end)?;
+    output.write(Some(val));
+    Ok(bytes)
+}
+
+/// `None` is serialized as the niche of `T` if it has one (see
+/// [`Poke::NICHE`]), otherwise `Option<T>` is a `u8` tag of 0 or 1 followed by
+/// the `T` if it's `Some`.
 unsafe impl<T: Poke> Poke for Option<T> {
-    const MAX_SIZE: usize = u8::MAX_SIZE + T::MAX_SIZE;
+    const MAX_SIZE: usize = if T::NICHE {
+        T::MAX_SIZE
+    } else {
+        u8::MAX_SIZE + T::MAX_SIZE
+    };
 
     #[inline]
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
         match self {
+            None if T::NICHE => T::poke_niche_into(bytes),
             None => 0u8.poke_into(bytes),
+            Some(ref v) if T::NICHE => v.poke_into(bytes),
             Some(ref v) => {
                 let bytes = 1u8.poke_into(bytes);
                 let bytes = v.poke_into(bytes);
@@ -359,37 +1320,89 @@ unsafe impl<T: Poke> Poke for Option<T> {
     }
 }
 
-impl<T: Default + Peek> Peek for Option<T> {
+impl<T: Peek> Peek for Option<T> {
     #[inline]
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-        let (variant, bytes) = peek_from_default::<u8>(bytes);
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        if T::NICHE {
+            return match T::peek_niche_from(bytes) {
+                Some(bytes) => {
+                    output.write(None);
+                    bytes
+                }
+                None => peek_some_from(bytes, output),
+            };
+        }
+        let (variant, bytes) = peek_from_uninit::<u8>(bytes);
         match variant {
             0 => {
-                *output = None;
-                bytes
-            }
-            1 => {
-                let (val, bytes) = peek_from_default(bytes);
-                *output = Some(val);
+                output.write(None);
                 bytes
             }
+            1 => peek_some_from(bytes, output),
             _ => unreachable!(),
         }
     }
 }
 
+impl<T: TryPeek> TryPeek for Option<T> {
+    #[inline]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        if T::NICHE {
+            return match T::try_peek_niche_from(bytes, end)? {
+                Some(bytes) => {
+                    output.write(None);
+                    Ok(bytes)
+                }
+                None => try_peek_some_from(bytes, end, output),
+            };
+        }
+        let (variant, bytes) = try_peek_from_uninit::<u8>(bytes, end)?;
+        match variant {
+            0 => {
+                output.write(None);
+                Ok(bytes)
+            }
+            1 => try_peek_some_from(bytes, end, output),
+            tag => Err(PeekError::InvalidOptionTag(tag)),
+        }
+    }
+}
+
+/// View an uninitialized array as an array of uninitialized elements.
+#[inline(always)]
+pub(crate) fn uninit_array<T, const N: usize>(
+    array: &mut MaybeUninit<[T; N]>,
+) -> &mut [MaybeUninit<T>; N] {
+    // Safety: `MaybeUninit<T>` has the same layout as `T`.
+    unsafe { &mut *(array as *mut MaybeUninit<[T; N]> as *mut [MaybeUninit<T>; N]) }
+}
+
 macro_rules! impl_for_arrays {
     ($($len:tt)+) => {
         $(unsafe impl<T: Poke> Poke for [T; $len] {
             const MAX_SIZE: usize = $len * T::MAX_SIZE;
+            const VERBATIM: bool = T::VERBATIM;
 
             unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
-                self.iter().fold(bytes, |bytes, e| e.poke_into(bytes))
+                poke_slice_into(self, bytes)
             }
         }
         impl<T: Peek> Peek for [T; $len] {
-            unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-                (&mut *output).iter_mut().fold(bytes, |bytes, e| <T>::peek_from(bytes, e))
+            unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+                peek_slice_from(bytes, uninit_array(output))
+            }
+        }
+        impl<T: TryPeek> TryPeek for [T; $len] {
+            unsafe fn try_peek_from(
+                bytes: *const u8,
+                end: *const u8,
+                output: &mut MaybeUninit<Self>,
+            ) -> Result<*const u8, PeekError> {
+                try_peek_slice_from(bytes, end, uninit_array(output))
             }
         })+
     }
@@ -404,17 +1417,28 @@ impl_for_arrays! {
 
 unsafe impl Poke for () {
     const MAX_SIZE: usize = 0;
+    const VERBATIM: bool = true;
 
     unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
         bytes
     }
 }
 impl Peek for () {
-    unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-        *output = ();
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        output.write(());
         bytes
     }
 }
+impl TryPeek for () {
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        _end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        output.write(());
+        Ok(bytes)
+    }
+}
 
 macro_rules! impl_for_tuple {
     ($($n:tt: $ty:ident),+) => {
@@ -427,11 +1451,27 @@ macro_rules! impl_for_tuple {
             }
         }
         impl<$($ty: Peek),+> Peek for ($($ty,)+) {
-            unsafe fn peek_from(bytes: *const u8, output: *mut Self) -> *const u8 {
-                $(let bytes = $ty::peek_from(bytes, &mut (*output).$n);)+
+            unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+                let output = output.as_mut_ptr();
+                $(let bytes = $ty::peek_from(bytes, uninit_field(ptr::addr_of_mut!((*output).$n)));)+
                 bytes
             }
         }
+        impl<$($ty: TryPeek),+> TryPeek for ($($ty,)+) {
+            unsafe fn try_peek_from(
+                bytes: *const u8,
+                end: *const u8,
+                output: &mut MaybeUninit<Self>,
+            ) -> Result<*const u8, PeekError> {
+                let output = output.as_mut_ptr();
+                $(let bytes = $ty::try_peek_from(
+                    bytes,
+                    end,
+                    uninit_field(ptr::addr_of_mut!((*output).$n)),
+                )?;)+
+                Ok(bytes)
+            }
+        }
     }
 }
 
@@ -440,3 +1480,25 @@ impl_for_tuple!(0: A, 1: B);
 impl_for_tuple!(0: A, 1: B, 2: C);
 impl_for_tuple!(0: A, 1: B, 2: C, 3: D);
 impl_for_tuple!(0: A, 1: B, 2: C, 3: D, 4: E);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_narrow_size() {
+        // Reading a `usize` on a 32-bit target with `fixed_usize` narrows a
+        // `u64` to a `u32`.
+        assert_eq!(narrow_size::<u64, u32>(u32::MAX as u64), Ok(u32::MAX));
+        assert_eq!(
+            narrow_size::<u64, u32>(u32::MAX as u64 + 1),
+            Err(PeekError::InvalidValue { type_name: "u32" })
+        );
+        assert_eq!(narrow_size::<i64, i32>(i32::MIN as i64), Ok(i32::MIN));
+        assert_eq!(
+            narrow_size::<i64, i32>(i32::MIN as i64 - 1),
+            Err(PeekError::InvalidValue { type_name: "i32" })
+        );
+        assert_eq!(narrow_size::<u64, usize>(7), Ok(7));
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::{peek_from_uninit, try_peek_from_uninit, Peek, PeekError, Poke, TryPeek};
+use core::{marker::PhantomData, mem::MaybeUninit};
+
+/// An `f32` serialized as an IEEE 754 half-precision float in 2 bytes.
+///
+/// Values are rounded to the nearest half, ties to even. The relative error
+/// is at most 2<sup>-11</sup> for magnitudes between 2<sup>-14</sup> and
+/// 65504, and the absolute error at most 2<sup>-25</sup> below that, where
+/// halves are subnormal. Larger magnitudes become infinite. Infinities and
+/// NaNs are kept, though NaN payloads are truncated.
+///
+/// Use `#[peek_poke(f16)]` to encode an `f32` field of a derived type this
+/// way, or the `f32`s of a field such as a `Point2D<f32>`.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct F16(pub f32);
+
+/// Round `v` to the bits of the nearest half-precision float.
+fn f32_to_f16(v: f32) -> u16 {
+    let bits = v.to_bits();
+    let sign = (bits >> 16) as u16 & 0x8000;
+    let exp = (bits >> 23 & 0xff) as i32;
+    let man = bits & 0x7f_ffff;
+
+    if exp == 0xff {
+        // Infinity, or NaN, which must keep a mantissa bit set.
+        let nan = if man != 0 {
+            0x200 | (man >> 13) as u16
+        } else {
+            0
+        };
+        return sign | 0x7c00 | nan;
+    }
+    let exp = exp - 127 + 15;
+    if exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+    // The truncated half, and the number of mantissa bits dropped, which
+    // decide which way to round. Rounding up may carry into the exponent,
+    // which gives the right result, up to infinity.
+    let (half, shift) = if exp > 0 {
+        ((exp as u32) << 10 | man >> 13, 13)
+    } else if exp >= -10 {
+        // Subnormal, so the implicit leading bit becomes explicit.
+        let shift = (14 - exp) as u32;
+        ((man | 0x80_0000) >> shift, shift)
+    } else {
+        return sign;
+    };
+    let rem = (man | 0x80_0000) & ((1 << shift) - 1);
+    let tie = 1 << (shift - 1);
+    let round_up = rem > tie || (rem == tie && half & 1 == 1);
+    sign | (half + round_up as u32) as u16
+}
+
+/// The value of the half-precision float with `bits`, which is exact.
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exp = u32::from(bits >> 10 & 0x1f);
+    let man = u32::from(bits & 0x3ff);
+    match exp {
+        0 => {
+            let v = man as f32 / (1 << 24) as f32;
+            if sign != 0 {
+                -v
+            } else {
+                v
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | man << 13),
+        _ => f32::from_bits(sign | (exp + 127 - 15) << 23 | man << 13),
+    }
+}
+
+unsafe impl Poke for F16 {
+    const MAX_SIZE: usize = u16::MAX_SIZE;
+
+    #[inline(always)]
+    unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+        f32_to_f16(self.0).poke_into(bytes)
+    }
+}
+
+impl Peek for F16 {
+    #[inline(always)]
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        let (half, ptr) = peek_from_uninit::<u16>(bytes);
+        output.write(F16(f16_to_f32(half)));
+        ptr
+    }
+}
+
+impl TryPeek for F16 {
+    #[inline(always)]
+    unsafe fn try_peek_from(
+        bytes: *const u8,
+        end: *const u8,
+        output: &mut MaybeUninit<Self>,
+    ) -> Result<*const u8, PeekError> {
+        let (half, ptr) = try_peek_from_uninit::<u16>(bytes, end)?;
+        output.write(F16(f16_to_f32(half)));
+        Ok(ptr)
+    }
+}
+
+/// An `f32` serialized as the integer `I`, in units of `1 / SCALE`.
+///
+/// Values are multiplied by `SCALE` and rounded to the nearest integer, ties
+/// away from zero, so the absolute error is at most `0.5 / SCALE` between
+/// `I::MIN / SCALE` and `I::MAX / SCALE`, plus the rounding of `f32`
+/// arithmetic. Values outside that range saturate to it, and NaN becomes 0.
+/// `I` is an integer of at most 32 bits, and `SCALE` must not be 0.
+///
+/// Use `#[peek_poke(fixed = I, scale = SCALE)]` to encode an `f32` field of a
+/// derived type this way, or the `f32`s of a field such as a `Point2D<f32>`.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Fixed<I, const SCALE: u32>(pub f32, PhantomData<I>);
+
+impl<I, const SCALE: u32> Fixed<I, SCALE> {
+    const SCALE: f32 = {
+        assert!(SCALE != 0, "Fixed SCALE must not be 0");
+        SCALE as f32
+    };
+
+    /// Wrap `v`, which `Fixed` can't be constructed with directly.
+    pub const fn new(v: f32) -> Self {
+        Fixed(v, PhantomData)
+    }
+}
+
+impl<I, const SCALE: u32> From<f32> for Fixed<I, SCALE> {
+    fn from(v: f32) -> Self {
+        Fixed::new(v)
+    }
+}
+
+macro_rules! impl_for_fixed {
+    ($($int:ty)+) => {
+        $(unsafe impl<const SCALE: u32> Poke for Fixed<$int, SCALE> {
+            const MAX_SIZE: usize = <$int>::MAX_SIZE;
+
+            #[inline(always)]
+            unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+                // Float to integer casts saturate, and turn NaN into 0.
+                ((self.0 * Self::SCALE).round() as $int).poke_into(bytes)
+            }
+        }
+        impl<const SCALE: u32> Peek for Fixed<$int, SCALE> {
+            #[inline(always)]
+            unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+                let (int, ptr) = peek_from_uninit::<$int>(bytes);
+                output.write(Fixed::new(int as f32 / Self::SCALE));
+                ptr
+            }
+        }
+        impl<const SCALE: u32> TryPeek for Fixed<$int, SCALE> {
+            #[inline(always)]
+            unsafe fn try_peek_from(
+                bytes: *const u8,
+                end: *const u8,
+                output: &mut MaybeUninit<Self>,
+            ) -> Result<*const u8, PeekError> {
+                let (int, ptr) = try_peek_from_uninit::<$int>(bytes, end)?;
+                output.write(Fixed::new(int as f32 / Self::SCALE));
+                Ok(ptr)
+            }
+        })+
+    };
+}
+
+impl_for_fixed! {
+    i8 i16 i32
+    u8 u16 u32
+}
+
+/// Types whose `f32`s can be encoded as `E`, an [`F16`] or a [`Fixed`], by
+/// reading and writing `Self` as `Self::Encoded`. This is how
+/// `#[peek_poke(f16)]` and `#[peek_poke(fixed = I, scale = SCALE)]` encode a
+/// field in place.
+///
+/// It's implemented for `f32`, arrays of `LossyF32` types, and with the
+/// `extras` feature, euclid's `#[repr(C)]` geometry types.
+///
+/// # Safety
+///
+/// `Self::Encoded` must have the same layout as `Self`, with each `f32`
+/// replaced by an `E`, which is `#[repr(transparent)]` over an `f32`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` doesn't hold `f32`s that can be encoded as `{E}`",
+    note = "`f16` and `fixed` apply to `f32`, arrays of it, and euclid's geometry types"
+)]
+pub unsafe trait LossyF32<E> {
+    /// `Self` with each `f32` replaced by an `E`.
+    type Encoded;
+}
+
+unsafe impl LossyF32<F16> for f32 {
+    type Encoded = F16;
+}
+
+unsafe impl<I, const SCALE: u32> LossyF32<Fixed<I, SCALE>> for f32 {
+    type Encoded = Fixed<I, SCALE>;
+}
+
+/// Arrays have no padding, so replacing their elements with ones of the same
+/// layout keeps the layout of the array.
+unsafe impl<T: LossyF32<E>, E, const N: usize> LossyF32<E> for [T; N] {
+    type Encoded = [T::Encoded; N];
+}
@@ -0,0 +1,159 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::{PeekError, TryPeek};
+use core::{borrow::Borrow, fmt, marker::PhantomData, mem::size_of, ops::Deref, ptr};
+
+/// A marker trait for plain-old-data types, whose serialized form is exactly
+/// their in-memory representation, so that they can be borrowed from a buffer
+/// of bytes instead of being deserialized. See [`PeekReader::read_ref()`].
+///
+/// It's implemented for the integer and float primitives when they're
+/// serialized in native byte order, which is always unless the
+/// `little_endian` feature is enabled on a big-endian target, and for arrays
+/// of `Pod` types. `usize` and `isize` are excluded when the `fixed_usize`
+/// feature makes them a different size on the wire. With the `extras`
+/// feature, it's also implemented for euclid's `#[repr(C)]` points, sizes,
+/// vectors and 3D transforms.
+///
+/// # Safety
+///
+/// Implementors must ensure that [`Poke::VERBATIM`] is set, and that `Self`
+/// upholds its contract: `MAX_SIZE` is `size_of::<Self>()`, `poke_into()`
+/// writes the bytes of `self` as they are in memory, which rules out padding,
+/// and any bytes are a valid `Self`, which rules out e.g. `bool`, `char` and
+/// enums.
+///
+/// [`PeekReader::read_ref()`]: crate::PeekReader::read_ref
+/// [`Poke::VERBATIM`]: crate::Poke::VERBATIM
+pub unsafe trait Pod: Copy + TryPeek {}
+
+macro_rules! impl_pod {
+    ($($ty:ty)+) => {
+        $(#[cfg(any(not(feature = "little_endian"), target_endian = "little"))]
+        unsafe impl Pod for $ty {})+
+    };
+}
+
+impl_pod! {
+    i8 i16 i32 i64
+    u8 u16 u32 u64
+    f32 f64
+}
+
+#[cfg(all(
+    any(not(feature = "little_endian"), target_endian = "little"),
+    any(not(feature = "fixed_usize"), target_pointer_width = "64")
+))]
+unsafe impl Pod for usize {}
+#[cfg(all(
+    any(not(feature = "little_endian"), target_endian = "little"),
+    any(not(feature = "fixed_usize"), target_pointer_width = "64")
+))]
+unsafe impl Pod for isize {}
+
+unsafe impl Pod for () {}
+unsafe impl<T> Pod for PhantomData<T> {}
+
+/// The size of an element is a multiple of its alignment, so arrays have no
+/// padding.
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] where [T; N]: TryPeek {}
+
+/// A `T`, or a slice of `T`s, borrowed from a buffer of bytes if it's suitably
+/// aligned, otherwise copied out of it.
+///
+/// Dereferences to the `T` either way.
+pub enum PeekRef<'a, T: ?Sized + ToOwned> {
+    /// A reference into the buffer.
+    Borrowed(&'a T),
+    /// A copy, made because the bytes weren't aligned for `T`.
+    Copied(T::Owned),
+}
+
+impl<'a, T: ?Sized + ToOwned> PeekRef<'a, T> {
+    /// Returns `true` if the value was borrowed rather than copied.
+    pub fn is_borrowed(&self) -> bool {
+        match *self {
+            PeekRef::Borrowed(_) => true,
+            PeekRef::Copied(_) => false,
+        }
+    }
+
+    /// Extract the owned value, copying it if it was borrowed.
+    pub fn into_owned(self) -> T::Owned {
+        match self {
+            PeekRef::Borrowed(v) => v.to_owned(),
+            PeekRef::Copied(v) => v,
+        }
+    }
+}
+
+impl<'a, T: ?Sized + ToOwned> Deref for PeekRef<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match *self {
+            PeekRef::Borrowed(v) => v,
+            PeekRef::Copied(ref v) => v.borrow(),
+        }
+    }
+}
+
+impl<'a, T: ?Sized + ToOwned + fmt::Debug> fmt::Debug for PeekRef<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// Borrow or copy `len` `T`s from the start of `bytes`, returning them and the
+/// rest of `bytes`.
+pub(crate) fn peek_slice_ref<T: Pod>(
+    bytes: &[u8],
+    len: usize,
+) -> Result<(PeekRef<'_, [T]>, &[u8]), PeekError> {
+    debug_assert!(T::VERBATIM);
+    let size = size_of::<T>()
+        .checked_mul(len)
+        .filter(|&size| size <= bytes.len())
+        .ok_or(PeekError::UnexpectedEnd)?;
+    let (head, rest) = bytes.split_at(size);
+    let ptr = head.as_ptr() as *const T;
+    let slice = if ptr.is_aligned() {
+        // Safety: the bytes are in bounds and aligned, and any bytes are a
+        // valid `T`.
+        PeekRef::Borrowed(unsafe { core::slice::from_raw_parts(ptr, len) })
+    } else {
+        let mut v = Vec::<T>::with_capacity(len);
+        // Safety: `v` has room for `len` `T`s, and any bytes are a valid `T`.
+        unsafe {
+            ptr::copy_nonoverlapping(head.as_ptr(), v.as_mut_ptr() as *mut u8, size);
+            v.set_len(len);
+        }
+        PeekRef::Copied(v)
+    };
+    Ok((slice, rest))
+}
+
+/// Borrow or copy a `T` from the start of `bytes`, returning it and the rest
+/// of `bytes`.
+pub(crate) fn peek_ref<T: Pod>(bytes: &[u8]) -> Result<(PeekRef<'_, T>, &[u8]), PeekError> {
+    debug_assert!(T::VERBATIM);
+    if bytes.len() < size_of::<T>() {
+        return Err(PeekError::UnexpectedEnd);
+    }
+    let ptr = bytes.as_ptr() as *const T;
+    // Safety: the bytes are in bounds, and any bytes are a valid `T`.
+    let v = if ptr.is_aligned() {
+        PeekRef::Borrowed(unsafe { &*ptr })
+    } else {
+        PeekRef::Copied(unsafe { ptr.read_unaligned() })
+    };
+    Ok((v, &bytes[size_of::<T>()..]))
+}
@@ -0,0 +1,147 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::{
+    pod::{peek_ref, peek_slice_ref},
+    PeekError, PeekRef, Pod, TryPeek,
+};
+use core::mem::MaybeUninit;
+
+/// A safe cursor for deserializing a sequence of values from a slice of bytes.
+///
+/// `PeekReader` uses [`TryPeek`], so the slice doesn't need a red zone and a
+/// truncated or corrupt buffer is reported as a [`PeekError`] instead of
+/// reading out of bounds.
+///
+/// # Example
+///
+/// ```
+/// use peek_poke::PeekReader;
+///
+/// let mut bytes = Vec::new();
+/// peek_poke::poke_into_vec(&42u32, &mut bytes);
+/// peek_poke::poke_into_vec(&Some(7u8), &mut bytes);
+///
+/// let mut reader = PeekReader::new(&bytes);
+/// assert_eq!(reader.read::<u32>(), Ok(42));
+/// assert_eq!(reader.read::<Option<u8>>(), Ok(Some(7)));
+/// assert!(reader.is_empty());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct PeekReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PeekReader<'a> {
+    /// Create a reader positioned at the start of `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        PeekReader { bytes, position: 0 }
+    }
+
+    /// Deserialize the next `T`, advancing past the bytes it occupied.
+    ///
+    /// On error the position of the reader is unchanged.
+    pub fn read<T: TryPeek>(&mut self) -> Result<T, PeekError> {
+        let range = self.bytes[self.position..].as_ptr_range();
+        let (val, end_ptr) = unsafe { crate::try_peek_from_uninit(range.start, range.end)? };
+        self.position += end_ptr as usize - range.start as usize;
+        Ok(val)
+    }
+
+    /// Deserialize the next `T` in place into `dst`, advancing past the bytes
+    /// it occupied.
+    ///
+    /// On error the position of the reader is unchanged and `dst` is left in
+    /// an unspecified, but valid, state.
+    pub fn read_into<T: TryPeek>(&mut self, dst: &mut T) -> Result<(), PeekError> {
+        let rest = crate::try_peek_from_slice(&self.bytes[self.position..], dst)?;
+        self.position = self.bytes.len() - rest.len();
+        Ok(())
+    }
+
+    /// Deserialize the next `dst.len()` `T`s in place into `dst`, with a
+    /// single copy if `T` is [`Poke::VERBATIM`](crate::Poke::VERBATIM),
+    /// advancing past the bytes they occupied.
+    ///
+    /// On error the position of the reader is unchanged and `dst` is left in
+    /// an unspecified, but valid, state.
+    pub fn read_slice_into<T: TryPeek>(&mut self, dst: &mut [T]) -> Result<(), PeekError> {
+        if T::VERBATIM {
+            let range = self.bytes[self.position..].as_ptr_range();
+            // Safety: verbatim types are plain data, so overwriting the
+            // elements of `dst` without dropping them leaks nothing.
+            let end_ptr = unsafe {
+                let dst = &mut *(dst as *mut [T] as *mut [MaybeUninit<T>]);
+                crate::try_peek_slice_from(range.start, range.end, dst)?
+            };
+            self.position += end_ptr as usize - range.start as usize;
+            return Ok(());
+        }
+        let position = self.position;
+        for e in dst {
+            if let Err(err) = self.read_into(e) {
+                self.position = position;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Borrow the next `T` from the bytes without deserializing it, advancing
+    /// past it. It's copied instead if the bytes aren't aligned for `T`.
+    ///
+    /// On error the position of the reader is unchanged.
+    pub fn read_ref<T: Pod>(&mut self) -> Result<PeekRef<'a, T>, PeekError> {
+        let (v, rest) = peek_ref(&self.bytes[self.position..])?;
+        self.position = self.bytes.len() - rest.len();
+        Ok(v)
+    }
+
+    /// Borrow the next `len` `T`s from the bytes without deserializing them,
+    /// advancing past them. They're copied instead if the bytes aren't aligned
+    /// for `T`.
+    ///
+    /// On error the position of the reader is unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use peek_poke::PeekReader;
+    ///
+    /// let mut bytes = Vec::new();
+    /// peek_poke::poke_extend_vec([1.0f32, 2.0, 3.0].iter(), &mut bytes);
+    ///
+    /// let mut reader = PeekReader::new(&bytes);
+    /// let floats = reader.read_slice_ref::<f32>(3).unwrap();
+    /// assert_eq!(*floats, [1.0, 2.0, 3.0]);
+    /// assert!(reader.is_empty());
+    /// ```
+    pub fn read_slice_ref<T: Pod>(&mut self, len: usize) -> Result<PeekRef<'a, [T]>, PeekError> {
+        let (v, rest) = peek_slice_ref(&self.bytes[self.position..], len)?;
+        self.position = self.bytes.len() - rest.len();
+        Ok(v)
+    }
+
+    /// The number of bytes that haven't been read yet.
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    /// Returns `true` if all of the bytes have been read.
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// The number of bytes read since the reader was created.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
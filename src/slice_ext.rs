@@ -9,10 +9,11 @@
 // except according to those terms.
 
 pub trait AsEndMutPtr<T> {
+    #[allow(clippy::wrong_self_convention)]
     fn as_end_mut_ptr(self) -> *mut T;
 }
 
-impl<'a> AsEndMutPtr<u8> for &'a mut [u8] {
+impl AsEndMutPtr<u8> for &mut [u8] {
     fn as_end_mut_ptr(self) -> *mut u8 {
         unsafe { self.as_mut_ptr().add(self.len()) }
     }
@@ -0,0 +1,158 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checkers for the contracts of `Peek` and `Poke` implementations.
+//!
+//! These are intended for use in tests of hand-written `unsafe impl Poke`s,
+//! where a wrong `MAX_SIZE` would otherwise silently corrupt memory.
+//!
+//! ```
+//! use peek_poke::{assert_encoding, testing, PeekPoke};
+//!
+//! #[derive(Debug, Default, PartialEq, PeekPoke)]
+//! struct Bar {
+//!     a: u8,
+//!     b: Option<u8>,
+//! }
+//!
+//! let bar = Bar { a: 1, b: Some(2) };
+//! testing::assert_max_size_honored(&bar);
+//! testing::assert_round_trip(&bar);
+//! assert_encoding!(bar, [1, 1, 2]);
+//! ```
+
+use crate::{Poke, TryPeek};
+use std::{any::type_name, fmt::Debug};
+
+/// Number of sentinel bytes placed either side of the buffer poked into by
+/// `assert_max_size_honored`. Writes further out of bounds than this are
+/// undefined behavior and won't be detected.
+const GUARD_SIZE: usize = 64;
+
+/// Serialize `value` into a `Vec` of exactly the bytes written.
+fn poke_to_vec<T: Poke>(value: &T) -> Vec<u8> {
+    assert_max_size_honored(value);
+    let mut bytes = Vec::new();
+    crate::poke_into_vec(value, &mut bytes);
+    bytes
+}
+
+/// Assert that `value` round trips through `poke_into()`, `peek_from()` and
+/// `try_peek_from()`, and that both readers consume exactly the bytes that
+/// were written.
+pub fn assert_round_trip<T>(value: &T)
+where
+    T: Debug + PartialEq + TryPeek,
+{
+    let name = type_name::<T>();
+    let bytes = poke_to_vec(value);
+
+    // `peek_from()` is allowed to read up to `MAX_SIZE` bytes, so give it a
+    // red zone.
+    let mut padded = bytes.clone();
+    crate::ensure_red_zone::<T>(&mut padded);
+    let (peeked, end_ptr) = unsafe { crate::peek_from_uninit::<T>(padded.as_ptr()) };
+    let read = end_ptr as usize - padded.as_ptr() as usize;
+    assert_eq!(
+        read,
+        bytes.len(),
+        "{}: peek_from() read {} bytes, but poke_into() wrote {}",
+        name,
+        read,
+        bytes.len()
+    );
+    assert_eq!(*value, peeked, "{}: peek_from() didn't round trip", name);
+
+    let range = bytes.as_ptr_range();
+    let try_peeked = match unsafe { crate::try_peek_from_uninit::<T>(range.start, range.end) } {
+        Ok((v, end_ptr)) => {
+            let unread = range.end as usize - end_ptr as usize;
+            assert!(
+                unread == 0,
+                "{}: try_peek_from() left {} of the {} bytes written unread",
+                name,
+                unread,
+                bytes.len()
+            );
+            v
+        }
+        Err(e) => panic!("{}: try_peek_from() failed: {}", name, e),
+    };
+    assert_eq!(
+        *value, try_peeked,
+        "{}: try_peek_from() didn't round trip",
+        name
+    );
+}
+
+/// Assert that `poke_into()` doesn't write more than `MAX_SIZE` bytes for
+/// `value`, and that the pointer it returns is consistent with what it wrote.
+///
+/// `value` is poked into a buffer of `MAX_SIZE` bytes surrounded by sentinel
+/// bytes, which are checked afterwards. This is done twice with different
+/// sentinels so that out of bounds writes of the sentinel value are caught.
+pub fn assert_max_size_honored<T: Poke>(value: &T) {
+    let name = type_name::<T>();
+    for &sentinel in &[0x00u8, 0xa5] {
+        let mut buf = vec![sentinel; GUARD_SIZE + T::MAX_SIZE + GUARD_SIZE];
+        let start = unsafe { buf.as_mut_ptr().add(GUARD_SIZE) };
+        let end_ptr = unsafe { value.poke_into(start) };
+        let before = &buf[..GUARD_SIZE];
+        let after = &buf[GUARD_SIZE + T::MAX_SIZE..];
+        let overrun = after.iter().rposition(|&b| b != sentinel);
+        assert!(
+            overrun.is_none(),
+            "{}: poke_into() wrote {} bytes past MAX_SIZE ({})",
+            name,
+            overrun.unwrap_or(0) + 1,
+            T::MAX_SIZE
+        );
+        assert!(
+            before.iter().all(|&b| b == sentinel),
+            "{}: poke_into() wrote before the start of the buffer",
+            name
+        );
+
+        let written = end_ptr as isize - start as isize;
+        assert!(
+            written >= 0 && written as usize <= T::MAX_SIZE,
+            "{}: poke_into() returned a pointer {} bytes from the start, but MAX_SIZE is {}",
+            name,
+            written,
+            T::MAX_SIZE
+        );
+    }
+}
+
+/// Assert that `value` serializes to exactly `expected`. Prefer the
+/// [`assert_encoding!`](crate::assert_encoding) macro.
+pub fn assert_encoding<T: Poke, B: AsRef<[u8]>>(value: &T, expected: B) {
+    let bytes = poke_to_vec(value);
+    assert_eq!(
+        bytes,
+        expected.as_ref(),
+        "{}: encoding doesn't match",
+        type_name::<T>()
+    );
+}
+
+/// Assert that a value serializes to an exact sequence of bytes.
+///
+/// ```
+/// use peek_poke::assert_encoding;
+///
+/// assert_encoding!(Some(7u8), [1, 7]);
+/// ```
+#[macro_export]
+macro_rules! assert_encoding {
+    ($value:expr, $expected:expr $(,)?) => {
+        $crate::testing::assert_encoding(&$value, $expected)
+    };
+}
@@ -0,0 +1,170 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::{Peek, PeekError, Poke, TryPeek, WireUsize};
+use core::mem::{size_of, MaybeUninit};
+
+/// An unsigned integer serialized as a LEB128 varint: 7 bits per byte, least
+/// significant first, with the high bit set on every byte but the last.
+///
+/// Small values take fewer bytes, but `MAX_SIZE` is larger than the size of
+/// the integer, e.g. 10 bytes for `u64`. Use `#[peek_poke(varint)]` to encode
+/// a field of a derived type this way.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Varint<T>(pub T);
+
+/// A signed integer serialized as a [`Varint`] after zigzag encoding, which
+/// maps `0, -1, 1, -2, 2, ...` to `0, 1, 2, 3, 4, ...`, so that values of
+/// small magnitude take few bytes whatever their sign.
+///
+/// Use `#[peek_poke(zigzag)]` to encode a field of a derived type this way.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ZigZag<T>(pub T);
+
+/// The number of bytes needed to encode a `bits` bit integer as a varint.
+const fn max_varint_len(bits: usize) -> usize {
+    bits.div_ceil(7)
+}
+
+#[inline]
+unsafe fn write_varint(mut v: u64, mut bytes: *mut u8) -> *mut u8 {
+    while v >= 0x80 {
+        *bytes = v as u8 | 0x80;
+        bytes = bytes.add(1);
+        v >>= 7;
+    }
+    *bytes = v as u8;
+    bytes.add(1)
+}
+
+/// Decode a varint of a `bits` bit integer from the `len` bytes at `bytes`.
+///
+/// Encodings that are longer than necessary, or that have bits set beyond
+/// `bits`, are rejected so that every value has exactly one encoding.
+#[inline]
+unsafe fn read_varint(
+    bytes: *const u8,
+    len: usize,
+    bits: usize,
+    type_name: &'static str,
+) -> Result<(u64, *const u8), PeekError> {
+    let max_len = max_varint_len(bits);
+    let mut v = 0u64;
+    for i in 0..max_len {
+        if i == len {
+            return Err(PeekError::UnexpectedEnd);
+        }
+        let byte = *bytes.add(i);
+        let payload = u64::from(byte & 0x7f);
+        let shift = 7 * i;
+        if bits - shift < 7 && payload >> (bits - shift) != 0 {
+            return Err(PeekError::InvalidValue { type_name });
+        }
+        v |= payload << shift;
+        if byte & 0x80 == 0 {
+            if byte == 0 && i > 0 {
+                return Err(PeekError::InvalidValue { type_name });
+            }
+            return Ok((v, bytes.add(i + 1)));
+        }
+    }
+    Err(PeekError::InvalidValue { type_name })
+}
+
+macro_rules! impl_for_varint {
+    ($($ty:ty: $wire:ty)+) => {
+        $(unsafe impl Poke for Varint<$ty> {
+            const MAX_SIZE: usize = max_varint_len(8 * size_of::<$wire>());
+
+            #[inline]
+            unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+                write_varint(self.0 as u64, bytes)
+            }
+        }
+        impl Peek for Varint<$ty> {
+            #[inline]
+            unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+                match Self::try_peek_from(bytes, bytes.add(Self::MAX_SIZE), output) {
+                    Ok(ptr) => ptr,
+                    Err(e) => panic!(
+                        concat!("WRDL: memory corruption detected while parsing Varint<",
+                            stringify!($ty), "> - {}"),
+                        e
+                    ),
+                }
+            }
+        }
+        impl TryPeek for Varint<$ty> {
+            #[inline]
+            unsafe fn try_peek_from(
+                bytes: *const u8,
+                end: *const u8,
+                output: &mut MaybeUninit<Self>,
+            ) -> Result<*const u8, PeekError> {
+                let type_name = concat!("Varint<", stringify!($ty), ">");
+                let len = end as usize - bytes as usize;
+                let (v, ptr) = read_varint(bytes, len, 8 * size_of::<$wire>(), type_name)?;
+                // Only fails for `usize` with the `fixed_usize` feature on a
+                // target with 32-bit pointers.
+                if v > <$ty>::MAX as u64 {
+                    return Err(PeekError::InvalidValue { type_name });
+                }
+                output.write(Varint(v as $ty));
+                Ok(ptr)
+            }
+        })+
+    };
+}
+
+impl_for_varint! {
+    u8: u8 u16: u16 u32: u32 u64: u64 usize: WireUsize
+}
+
+macro_rules! impl_for_zigzag {
+    ($($ty:ty: $uty:ty)+) => {
+        $(unsafe impl Poke for ZigZag<$ty> {
+            const MAX_SIZE: usize = Varint::<$uty>::MAX_SIZE;
+
+            #[inline]
+            unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+                let v = self.0;
+                let zigzag = ((v << 1) ^ (v >> (<$ty>::BITS - 1))) as $uty;
+                Varint(zigzag).poke_into(bytes)
+            }
+        }
+        impl Peek for ZigZag<$ty> {
+            #[inline]
+            unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+                let (Varint(zigzag), ptr) = crate::peek_from_uninit::<Varint<$uty>>(bytes);
+                output.write(ZigZag((zigzag >> 1) as $ty ^ -((zigzag & 1) as $ty)));
+                ptr
+            }
+        }
+        impl TryPeek for ZigZag<$ty> {
+            #[inline]
+            unsafe fn try_peek_from(
+                bytes: *const u8,
+                end: *const u8,
+                output: &mut MaybeUninit<Self>,
+            ) -> Result<*const u8, PeekError> {
+                let (Varint(zigzag), ptr) =
+                    crate::try_peek_from_uninit::<Varint<$uty>>(bytes, end)?;
+                output.write(ZigZag((zigzag >> 1) as $ty ^ -((zigzag & 1) as $ty)));
+                Ok(ptr)
+            }
+        })+
+    };
+}
+
+impl_for_zigzag! {
+    i8: u8 i16: u16 i32: u32 i64: u64 isize: usize
+}
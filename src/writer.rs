@@ -0,0 +1,156 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::{vec_ext::*, Poke, PokeError};
+use core::{mem::MaybeUninit, slice};
+
+enum Target<'a> {
+    /// Append to a `Vec`, growing it as required. `usize` is the length of the
+    /// `Vec` when the writer was created.
+    Vec(&'a mut Vec<u8>, usize),
+    /// Write into a fixed-size buffer. Only the first `len` bytes are known to
+    /// be initialized.
+    Slice(&'a mut [MaybeUninit<u8>]),
+}
+
+/// A safe cursor for serializing a sequence of values into a `Vec<u8>`, a
+/// `&mut [u8]` or a `&mut [MaybeUninit<u8>]`, such as the spare capacity of a
+/// `Vec` returned by `Vec::spare_capacity_mut()`.
+///
+/// Appending to a `Vec` grows it with `try_reserve`, so allocation failure is
+/// reported as [`PokeError::AllocError`]. Writing into a fixed-size buffer
+/// reports [`PokeError::BufferTooSmall`] when the serialized value doesn't fit
+/// in the space that is left.
+///
+/// # Example
+///
+/// ```
+/// use peek_poke::PokeWriter;
+///
+/// let mut buf = [0u8; 16];
+/// let mut writer = PokeWriter::from_slice(&mut buf);
+/// writer.write(&42u32).unwrap();
+/// writer.extend(&[1u8, 2, 3]).unwrap();
+/// assert_eq!(writer.len(), 7);
+/// assert_eq!(writer.finish().len(), 7);
+/// ```
+pub struct PokeWriter<'a> {
+    target: Target<'a>,
+    len: usize,
+}
+
+impl<'a> PokeWriter<'a> {
+    /// Create a writer that appends to the end of `vec`.
+    pub fn from_vec(vec: &'a mut Vec<u8>) -> Self {
+        let start = vec.len();
+        PokeWriter {
+            target: Target::Vec(vec, start),
+            len: 0,
+        }
+    }
+
+    /// Create a writer that writes into `buf`, starting at the beginning.
+    pub fn from_slice(buf: &'a mut [u8]) -> Self {
+        // Safety: The writer only ever writes initialized bytes, so `buf`
+        // can't be observed to contain uninitialized bytes afterwards.
+        let buf = unsafe {
+            slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut MaybeUninit<u8>, buf.len())
+        };
+        Self::from_uninit(buf)
+    }
+
+    /// Create a writer that writes into the possibly uninitialized `buf`,
+    /// starting at the beginning.
+    pub fn from_uninit(buf: &'a mut [MaybeUninit<u8>]) -> Self {
+        PokeWriter {
+            target: Target::Slice(buf),
+            len: 0,
+        }
+    }
+
+    /// Serialize `value` after the bytes already written.
+    ///
+    /// On error nothing is written.
+    pub fn write<T: Poke>(&mut self, value: &T) -> Result<(), PokeError> {
+        let written = match self.target {
+            Target::Vec(ref mut vec, _) => {
+                vec.try_reserve(T::MAX_SIZE)?;
+                let len = vec.len();
+                unsafe {
+                    let end_ptr = crate::checked_poke_into(value, vec.as_mut_ptr().add(len));
+                    // Checks that `end_ptr` is within the reserved capacity.
+                    vec.set_end_ptr(end_ptr);
+                }
+                vec.len() - len
+            }
+            Target::Slice(ref mut buf) => {
+                let available = buf.len() - self.len;
+                unsafe {
+                    let ptr = buf.as_mut_ptr().add(self.len) as *mut u8;
+                    crate::try_poke_into_ptr(value, ptr, available)?
+                }
+            }
+        };
+        self.len += written;
+        Ok(())
+    }
+
+    /// Serialize each item of `iter` in turn, returning the number of items
+    /// written.
+    ///
+    /// On error, the length of the writer is restored to what it was before
+    /// the call.
+    pub fn extend<I>(&mut self, iter: I) -> Result<usize, PokeError>
+    where
+        I: IntoIterator,
+        I::Item: Poke,
+    {
+        let len = self.len;
+        let mut count = 0;
+        for item in iter {
+            if let Err(e) = self.write(&item) {
+                self.truncate(len);
+                return Err(e);
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// The number of bytes written.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no bytes have been written.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Finish writing, returning the bytes that were written.
+    pub fn finish(self) -> &'a mut [u8] {
+        match self.target {
+            Target::Vec(vec, start) => &mut vec[start..],
+            // Safety: The first `len` bytes have all been written, and `Poke`
+            // guarantees that `poke_into()` initializes every byte it skips.
+            Target::Slice(buf) => unsafe {
+                slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, self.len)
+            },
+        }
+    }
+
+    fn truncate(&mut self, len: usize) {
+        debug_assert!(len <= self.len);
+        if let Target::Vec(ref mut vec, start) = self.target {
+            vec.truncate(start + len);
+        }
+        self.len = len;
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+mod common;
+
+use common::poke;
+use peek_poke::{BitArray, PeekError, PeekPoke, PeekReader, Poke};
+
+type Flag = bool;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Flags {
+    a: bool,
+    b: bool,
+    c: bool,
+    x: u16,
+    d: bool,
+    #[peek_poke(bits)]
+    e: Flag,
+}
+
+#[test]
+fn test_packed_bools() {
+    assert_eq!(Flags::MAX_SIZE, 1 + 2 + 1);
+    let flags = Flags {
+        a: true,
+        b: false,
+        c: true,
+        x: 0x1234,
+        d: false,
+        e: true,
+    };
+    let bytes = poke(&flags);
+    assert_eq!(bytes[0], 0b101);
+    assert_eq!(bytes[3], 0b10);
+    assert_eq!(PeekReader::new(&bytes).read(), Ok(flags));
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Nine(bool, bool, bool, bool, bool, bool, bool, bool, bool);
+
+#[test]
+fn test_packed_bools_overflow() {
+    assert_eq!(Nine::MAX_SIZE, 2);
+    let nine = Nine(false, false, false, false, false, false, false, true, true);
+    assert_eq!(poke(&nine), [0x80, 0x01]);
+    assert_eq!(PeekReader::new(&poke(&nine)).read(), Ok(nine));
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+enum Item {
+    #[default]
+    Empty,
+    Rect {
+        opaque: bool,
+        clipped: bool,
+        width: u32,
+    },
+}
+
+#[test]
+fn test_packed_bools_in_enum() {
+    assert_eq!(Item::MAX_SIZE, 1 + 1 + 4);
+    let item = Item::Rect {
+        opaque: false,
+        clipped: true,
+        width: 7,
+    };
+    assert_eq!(poke(&item)[..2], [1, 0b10]);
+    assert_eq!(PeekReader::new(&poke(&item)).read(), Ok(item));
+}
+
+#[test]
+fn test_bit_array() {
+    assert_eq!(BitArray::<0>::MAX_SIZE, 0);
+    assert_eq!(BitArray::<8>::MAX_SIZE, 1);
+    assert_eq!(BitArray::<12>::MAX_SIZE, 2);
+
+    let mut bits = BitArray([false; 12]);
+    bits.0[0] = true;
+    bits.0[9] = true;
+    assert_eq!(poke(&bits), [0x01, 0x02]);
+    assert_eq!(PeekReader::new(&poke(&bits)).read(), Ok(bits));
+    assert_eq!(
+        PeekReader::new(&[0x01]).read::<BitArray<12>>(),
+        Err(PeekError::UnexpectedEnd)
+    );
+}
+
+#[test]
+fn test_bit_array_padding() {
+    let res = PeekReader::new(&[0x00, 0x10]).read::<BitArray<12>>();
+    if cfg!(feature = "strict") {
+        assert_eq!(
+            res,
+            Err(PeekError::InvalidValue {
+                type_name: "BitArray"
+            })
+        );
+    } else {
+        assert_eq!(res, Ok(BitArray([false; 12])));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Mask {
+    #[peek_poke(bits)]
+    sides: [bool; 4],
+    unpacked: [bool; 2],
+}
+
+#[test]
+fn test_bits_attribute_on_array() {
+    assert_eq!(Mask::MAX_SIZE, 1 + 2);
+    let mask = Mask {
+        sides: [true, false, false, true],
+        unpacked: [true, true],
+    };
+    assert_eq!(poke(&mask), [0b1001, 1, 1]);
+    assert_eq!(PeekReader::new(&poke(&mask)).read(), Ok(mask));
+}
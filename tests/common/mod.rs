@@ -0,0 +1,19 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use peek_poke::Poke;
+
+/// Serialize `value` into a `Vec` of exactly the bytes written.
+pub fn poke<T: Poke>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    peek_poke::poke_into_vec(value, &mut bytes);
+    assert!(bytes.len() <= T::MAX_SIZE);
+    bytes
+}
@@ -0,0 +1,77 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+mod common;
+
+use common::poke;
+use peek_poke::{Be, Le, PeekPoke, PeekReader};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Header {
+    magic: Be<u32>,
+    len: Le<u16>,
+    scale: Be<f32>,
+}
+
+#[cfg(feature = "little_endian")]
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+enum Tagged {
+    #[default]
+    A,
+    B(u32),
+}
+
+#[test]
+fn test_wrappers_encoding() {
+    assert_eq!(poke(&Le(0x0102_0304u32)), [4, 3, 2, 1]);
+    assert_eq!(poke(&Be(0x0102_0304u32)), [1, 2, 3, 4]);
+    assert_eq!(poke(&Be(-2i16)), [0xff, 0xfe]);
+    assert_eq!(poke(&Be(1.0f32)), [0x3f, 0x80, 0, 0]);
+    assert_eq!(poke(&Le(1.0f64)), [0, 0, 0, 0, 0, 0, 0xf0, 0x3f]);
+}
+
+#[test]
+fn test_wrappers_round_trip() {
+    let header = Header {
+        magic: Be(0xcafe_f00d),
+        len: Le(0x1234),
+        scale: Be(0.5),
+    };
+    let bytes = poke(&header);
+    assert_eq!(bytes[..6], [0xca, 0xfe, 0xf0, 0x0d, 0x34, 0x12]);
+    let mut reader = PeekReader::new(&bytes);
+    assert_eq!(reader.read::<Header>(), Ok(header));
+    assert!(reader.is_empty());
+
+    let mut padded = bytes.clone();
+    peek_poke::ensure_red_zone::<Header>(&mut padded);
+    let (output, _) = unsafe { peek_poke::peek_from_uninit::<Header>(padded.as_ptr()) };
+    assert_eq!(output, header);
+}
+
+#[test]
+#[cfg(feature = "little_endian")]
+fn test_little_endian_feature() {
+    assert_eq!(poke(&0x0102_0304u32), [4, 3, 2, 1]);
+    assert_eq!(poke(&1.0f32), [0, 0, 0x80, 0x3f]);
+    assert_eq!(poke(&Some(0x0102u16)), [1, 2, 1]);
+    assert_eq!(poke(&Tagged::B(0x0102_0304)), [1, 4, 3, 2, 1]);
+    assert_eq!(
+        PeekReader::new(&[4, 3, 2, 1]).read::<u32>(),
+        Ok(0x0102_0304)
+    );
+}
+
+#[test]
+#[cfg(not(feature = "little_endian"))]
+fn test_native_endian() {
+    assert_eq!(poke(&0x0102_0304u32), 0x0102_0304u32.to_ne_bytes());
+    assert_eq!(poke(&1.0f32), 1.0f32.to_ne_bytes());
+}
@@ -0,0 +1,245 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+mod common;
+
+use common::poke;
+use peek_poke::{PeekError, PeekPoke, PeekReader, Poke};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+enum Explicit {
+    #[default]
+    A = 5,
+    B,
+    C = 10,
+    D,
+}
+
+#[test]
+fn test_explicit_discriminants() {
+    assert_eq!(Explicit::MAX_SIZE, 1);
+    assert_eq!(poke(&Explicit::A), [5]);
+    assert_eq!(poke(&Explicit::B), [6]);
+    assert_eq!(poke(&Explicit::C), [10]);
+    assert_eq!(poke(&Explicit::D), [11]);
+    for &v in &[Explicit::A, Explicit::B, Explicit::C, Explicit::D] {
+        assert_eq!(PeekReader::new(&poke(&v)).read::<Explicit>(), Ok(v));
+    }
+    // The variant indexes and the gap are not valid tags.
+    for &tag in &[0u8, 1, 7, 12] {
+        assert_eq!(
+            PeekReader::new(&[tag]).read::<Explicit>(),
+            Err(PeekError::InvalidTag {
+                type_name: "Explicit",
+                tag: tag as u64,
+            })
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "Explicit - invalid enum tag 7")]
+fn test_invalid_tag_panics() {
+    let mut bytes = vec![7];
+    peek_poke::ensure_red_zone::<Explicit>(&mut bytes);
+    unsafe {
+        peek_poke::peek_from_default::<Explicit>(bytes.as_ptr());
+    }
+}
+
+#[repr(u16)]
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+enum Repr {
+    #[default]
+    A,
+    B(u8) = 0x0102,
+}
+
+#[repr(i8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+enum Signed {
+    #[default]
+    Minus = -1,
+    Zero,
+    One,
+}
+
+#[test]
+fn test_repr() {
+    assert_eq!(Repr::MAX_SIZE, 2 + 1);
+    assert_eq!(poke(&Repr::A), 0u16.to_ne_bytes());
+    let bytes = poke(&Repr::B(3));
+    assert_eq!(bytes[..2], 0x0102u16.to_ne_bytes());
+    assert_eq!(PeekReader::new(&bytes).read::<Repr>(), Ok(Repr::B(3)));
+
+    assert_eq!(poke(&Signed::Minus), [0xff]);
+    assert_eq!(poke(&Signed::Zero), [0]);
+    assert_eq!(poke(&Signed::One), [1]);
+    assert_eq!(PeekReader::new(&[0xff]).read::<Signed>(), Ok(Signed::Minus));
+}
+
+// Without `#[repr(...)]`, the tag type covers the discriminants.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+enum Wide {
+    #[default]
+    A = 1000,
+    B,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+enum Negative {
+    #[default]
+    A = -200,
+    B,
+    C = 1,
+}
+
+#[test]
+fn test_inferred_tag_type() {
+    assert_eq!(Wide::MAX_SIZE, 2);
+    assert_eq!(poke(&Wide::A), 1000u16.to_ne_bytes());
+    assert_eq!(poke(&Wide::B), 1001u16.to_ne_bytes());
+    for &v in &[Wide::A, Wide::B] {
+        assert_eq!(PeekReader::new(&poke(&v)).read::<Wide>(), Ok(v));
+    }
+
+    assert_eq!(Negative::MAX_SIZE, 2);
+    assert_eq!(poke(&Negative::A), (-200i16).to_ne_bytes());
+    assert_eq!(poke(&Negative::B), (-199i16).to_ne_bytes());
+    assert_eq!(poke(&Negative::C), 1i16.to_ne_bytes());
+    for &v in &[Negative::A, Negative::B, Negative::C] {
+        assert_eq!(PeekReader::new(&poke(&v)).read::<Negative>(), Ok(v));
+    }
+}
+
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+#[peek_poke(tag = u16)]
+enum Pinned {
+    #[default]
+    A,
+    B(u32),
+}
+
+// The same enum, with a variant inserted at the front and `B` keeping its
+// tag, so that the two are compatible on the wire.
+#[repr(u8)]
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+#[peek_poke(tag = u16)]
+enum PinnedV2 {
+    #[default]
+    New = 2,
+    A = 0,
+    B(u32),
+}
+
+#[repr(u8)]
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+#[peek_poke(tag = u32)]
+enum Overridden {
+    #[default]
+    A,
+}
+
+#[test]
+fn test_tag_attribute() {
+    assert_eq!(Pinned::MAX_SIZE, 2 + 4);
+    assert_eq!(Overridden::MAX_SIZE, 4);
+    let bytes = poke(&Pinned::B(7));
+    assert_eq!(bytes[..2], 1u16.to_ne_bytes());
+    assert_eq!(
+        PeekReader::new(&bytes).read::<PinnedV2>(),
+        Ok(PinnedV2::B(7))
+    );
+    assert_eq!(
+        PeekReader::new(&poke(&PinnedV2::A)).read::<Pinned>(),
+        Ok(Pinned::A)
+    );
+}
+
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+enum UnitOther {
+    #[default]
+    A,
+    B(u32),
+    #[peek_poke(other)]
+    Unknown,
+}
+
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+#[peek_poke(tag = u16)]
+enum TagOther {
+    #[default]
+    A,
+    B(u8),
+    #[peek_poke(other)]
+    Unknown(u16),
+}
+
+// A newer version of `TagOther`, with a variant its readers don't know.
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+#[peek_poke(tag = u16)]
+enum TagOtherV2 {
+    #[default]
+    A,
+    B(u8),
+    C,
+    D,
+}
+
+#[test]
+fn test_unit_other() {
+    assert_eq!(UnitOther::MAX_SIZE, 1 + 4);
+    assert_eq!(poke(&UnitOther::Unknown), [2]);
+    for &tag in &[2u8, 3, 255] {
+        assert_eq!(
+            PeekReader::new(&[tag]).read::<UnitOther>(),
+            Ok(UnitOther::Unknown)
+        );
+    }
+    let mut bytes = vec![200];
+    peek_poke::ensure_red_zone::<UnitOther>(&mut bytes);
+    let (value, end) = unsafe { peek_poke::peek_from_default::<UnitOther>(bytes.as_ptr()) };
+    assert_eq!(value, UnitOther::Unknown);
+    assert_eq!(end, unsafe { bytes.as_ptr().add(1) });
+    assert_eq!(
+        PeekReader::new(&poke(&UnitOther::B(1))).read::<UnitOther>(),
+        Ok(UnitOther::B(1))
+    );
+}
+
+#[test]
+fn test_tag_carrying_other() {
+    // Only the tag is written, so the field doesn't count towards the size.
+    assert_eq!(TagOther::MAX_SIZE, 2 + 1);
+
+    let mut bytes_v2 = Vec::new();
+    for v in &[
+        TagOtherV2::A,
+        TagOtherV2::B(9),
+        TagOtherV2::C,
+        TagOtherV2::D,
+    ] {
+        peek_poke::poke_into_vec(v, &mut bytes_v2);
+    }
+    let mut reader = PeekReader::new(&bytes_v2);
+    assert_eq!(reader.read::<TagOther>(), Ok(TagOther::A));
+    assert_eq!(reader.read::<TagOther>(), Ok(TagOther::B(9)));
+    assert_eq!(reader.read::<TagOther>(), Ok(TagOther::Unknown(2)));
+    let unknown = reader.read::<TagOther>().unwrap();
+    assert_eq!(unknown, TagOther::Unknown(3));
+    assert!(reader.is_empty());
+
+    // Unknown tags are written back unchanged.
+    assert_eq!(poke(&unknown), 3u16.to_ne_bytes());
+    assert_eq!(
+        PeekReader::new(&poke(&unknown)).read::<TagOtherV2>(),
+        Ok(TagOtherV2::D)
+    );
+}
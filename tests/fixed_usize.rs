@@ -0,0 +1,61 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![cfg(feature = "fixed_usize")]
+
+mod common;
+
+use common::poke;
+use peek_poke::{Be, Le, PeekPoke, PeekReader, Poke};
+
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+struct SpatialId(usize, isize);
+
+#[test]
+fn test_max_size() {
+    assert_eq!(usize::MAX_SIZE, 8);
+    assert_eq!(isize::MAX_SIZE, 8);
+    assert_eq!(Le::<usize>::MAX_SIZE, 8);
+    assert_eq!(Be::<isize>::MAX_SIZE, 8);
+    assert_eq!(SpatialId::MAX_SIZE, 16);
+}
+
+#[test]
+fn test_encoding() {
+    assert_eq!(poke(&7usize), poke(&7u64));
+    assert_eq!(poke(&-1isize), [0xff; 8]);
+    assert_eq!(poke(&Be(0x0102usize)), [0, 0, 0, 0, 0, 0, 1, 2]);
+    assert_eq!(poke(&Le(0x0102usize)), [2, 1, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn test_round_trip() {
+    let id = SpatialId(usize::MAX, isize::MIN);
+    let bytes = poke(&id);
+    assert_eq!(bytes.len(), 16);
+    assert_eq!(PeekReader::new(&bytes).read::<SpatialId>(), Ok(id));
+}
+
+#[test]
+#[cfg(target_pointer_width = "32")]
+fn test_out_of_range() {
+    use peek_poke::PeekError;
+
+    let bytes = poke(&(u32::MAX as u64 + 1));
+    assert_eq!(
+        PeekReader::new(&bytes).read::<usize>(),
+        Err(PeekError::InvalidValue { type_name: "usize" })
+    );
+    let bytes = poke(&(i32::MIN as i64 - 1));
+    assert_eq!(
+        PeekReader::new(&bytes).read::<isize>(),
+        Err(PeekError::InvalidValue { type_name: "isize" })
+    );
+}
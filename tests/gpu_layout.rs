@@ -0,0 +1,215 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+mod common;
+
+use common::poke;
+use peek_poke::{Aligned, GpuLayout, GpuVec, PeekPoke, PeekReader, Poke, Std140, Std430};
+
+/// A buffer of `len` zeroes, with `f32`s written at the given offsets.
+fn expected(len: usize, values: &[(usize, f32)]) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    for &(offset, v) in values {
+        bytes[offset..offset + 4].copy_from_slice(&v.to_ne_bytes());
+    }
+    bytes
+}
+
+fn mat4() -> [GpuVec<f32, 4>; 4] {
+    let mut m = [GpuVec([0.0; 4]); 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, v) in row.0.iter_mut().enumerate() {
+            *v = (10 + 4 * i + j) as f32;
+        }
+    }
+    m
+}
+
+macro_rules! uniforms {
+    ($name:ident, $layout:ident) => {
+        // layout(std140) uniform Uniforms {
+        //     float a; vec2 b; vec3 c; float d; float e[2]; mat4 m; bool f;
+        // };
+        #[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+        #[peek_poke(layout = $layout)]
+        struct $name {
+            a: f32,
+            b: GpuVec<f32, 2>,
+            c: GpuVec<f32, 3>,
+            d: f32,
+            e: [f32; 2],
+            m: [GpuVec<f32, 4>; 4],
+            f: bool,
+        }
+
+        impl $name {
+            fn new() -> Self {
+                $name {
+                    a: 1.0,
+                    b: GpuVec([2.0, 3.0]),
+                    c: GpuVec([4.0, 5.0, 6.0]),
+                    d: 7.0,
+                    e: [8.0, 9.0],
+                    m: mat4(),
+                    f: true,
+                }
+            }
+        }
+    };
+}
+
+uniforms!(Uniforms140, std140);
+uniforms!(Uniforms430, std430);
+
+/// The offsets of `a` to `e`, which are the same in both layouts.
+fn head(e0: usize, e1: usize) -> Vec<(usize, f32)> {
+    vec![
+        (0, 1.0),
+        (8, 2.0),
+        (12, 3.0),
+        (16, 4.0),
+        (20, 5.0),
+        (24, 6.0),
+        (28, 7.0),
+        (e0, 8.0),
+        (e1, 9.0),
+    ]
+}
+
+fn with_mat4(mut values: Vec<(usize, f32)>, offset: usize) -> Vec<(usize, f32)> {
+    values.extend((0..16).map(|i| (offset + 4 * i, (10 + i) as f32)));
+    values
+}
+
+#[test]
+fn test_std140() {
+    assert_eq!(<Uniforms140 as GpuLayout<Std140>>::ALIGN, 16);
+    assert_eq!(Uniforms140::MAX_SIZE, 144);
+    let mut bytes = expected(144, &with_mat4(head(32, 48), 64));
+    bytes[128] = 1;
+    let uniforms = Uniforms140::new();
+    assert_eq!(poke(&uniforms), bytes);
+    assert_eq!(PeekReader::new(&bytes).read(), Ok(uniforms));
+}
+
+#[test]
+fn test_std430() {
+    assert_eq!(<Uniforms430 as GpuLayout<Std430>>::ALIGN, 16);
+    assert_eq!(Uniforms430::MAX_SIZE, 128);
+    let mut bytes = expected(128, &with_mat4(head(32, 36), 48));
+    bytes[112] = 1;
+    let uniforms = Uniforms430::new();
+    assert_eq!(poke(&uniforms), bytes);
+    assert_eq!(PeekReader::new(&bytes).read(), Ok(uniforms));
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+#[peek_poke(layout = std430)]
+struct Light {
+    radius: f32,
+    color: GpuVec<f32, 3>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+#[peek_poke(layout = std430)]
+struct Scene {
+    count: u32,
+    lights: [Light; 2],
+    weights: [f32; 3],
+}
+
+#[test]
+fn test_nested_structs() {
+    // `color` is aligned to 16, so `Light` is 32 bytes.
+    assert_eq!(Light::MAX_SIZE, 32);
+    assert_eq!(Scene::MAX_SIZE, 16 + 2 * 32 + 16);
+    let scene = Scene {
+        count: 2,
+        lights: [
+            Light {
+                radius: 1.0,
+                color: GpuVec([2.0, 3.0, 4.0]),
+            },
+            Light {
+                radius: 5.0,
+                color: GpuVec([6.0, 7.0, 8.0]),
+            },
+        ],
+        weights: [9.0, 10.0, 11.0],
+    };
+    let mut bytes = expected(
+        96,
+        &[
+            (16, 1.0),
+            (32, 2.0),
+            (36, 3.0),
+            (40, 4.0),
+            (48, 5.0),
+            (64, 6.0),
+            (68, 7.0),
+            (72, 8.0),
+            (80, 9.0),
+            (84, 10.0),
+            (88, 11.0),
+        ],
+    );
+    bytes[..4].copy_from_slice(&2u32.to_ne_bytes());
+    assert_eq!(poke(&scene), bytes);
+    assert_eq!(PeekReader::new(&bytes).read(), Ok(scene));
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Item {
+    id: u8,
+    clip: Aligned<[f32; 2], Std140>,
+}
+
+#[test]
+fn test_aligned_wrapper() {
+    assert_eq!(Item::MAX_SIZE, 1 + 32);
+    let item = Item {
+        id: 7,
+        clip: Aligned::new([1.0, 2.0]),
+    };
+    let bytes = poke(&item);
+    assert_eq!(bytes[1..], expected(32, &[(0, 1.0), (16, 2.0)])[..]);
+    assert_eq!(PeekReader::new(&bytes).read(), Ok(item));
+}
+
+#[cfg(feature = "extras")]
+#[test]
+fn test_euclid() {
+    use euclid::default::{Point2D, Transform3D};
+
+    #[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+    #[peek_poke(layout = std140)]
+    struct Transform {
+        offset: Point2D<f32>,
+        transform: Transform3D<f32>,
+    }
+
+    assert_eq!(Transform::MAX_SIZE, 16 + 64);
+    let transform = Transform {
+        offset: Point2D::new(1.0, 2.0),
+        transform: Transform3D::create_translation(3.0, 4.0, 5.0),
+    };
+    let mut values = vec![(0, 1.0), (4, 2.0)];
+    values.extend(
+        transform
+            .transform
+            .to_row_major_array()
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (16 + 4 * i, v)),
+    );
+    let bytes = poke(&transform);
+    assert_eq!(bytes, expected(80, &values));
+    assert_eq!(PeekReader::new(&bytes).read(), Ok(transform));
+}
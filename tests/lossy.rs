@@ -0,0 +1,167 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+mod common;
+
+use common::poke;
+use peek_poke::{Fixed, PeekPoke, PeekReader, Poke, F16};
+
+fn f16_bits(v: f32) -> u16 {
+    let bytes = poke(&F16(v));
+    PeekReader::new(&bytes).read::<u16>().unwrap()
+}
+
+fn f16_round_trip(v: f32) -> f32 {
+    PeekReader::new(&poke(&F16(v))).read::<F16>().unwrap().0
+}
+
+#[test]
+fn test_f16_values() {
+    assert_eq!(F16::MAX_SIZE, 2);
+    assert_eq!(f16_bits(0.0), 0x0000);
+    assert_eq!(f16_bits(-0.0), 0x8000);
+    assert_eq!(f16_bits(1.0), 0x3c00);
+    assert_eq!(f16_bits(-2.0), 0xc000);
+    assert_eq!(f16_bits(65504.0), 0x7bff);
+    assert_eq!(f16_bits(f32::INFINITY), 0x7c00);
+    assert_eq!(f16_bits(f32::NEG_INFINITY), 0xfc00);
+    // Smallest subnormal, and half of it, which rounds to even.
+    assert_eq!(f16_bits(2f32.powi(-24)), 0x0001);
+    assert_eq!(f16_bits(2f32.powi(-25)), 0x0000);
+    assert_eq!(f16_bits(1.5 * 2f32.powi(-25)), 0x0001);
+    assert!(f16_round_trip(f32::NAN).is_nan());
+}
+
+#[test]
+fn test_f16_rounding() {
+    // Halfway between 1.0 and the next half rounds to even, i.e. down, and
+    // anything above it up.
+    let ulp = 2f32.powi(-10);
+    assert_eq!(f16_round_trip(1.0 + ulp / 2.0), 1.0);
+    assert_eq!(f16_round_trip(1.0 + ulp * 1.5), 1.0 + ulp * 2.0);
+    assert_eq!(f16_round_trip(1.0 + ulp * 0.75), 1.0 + ulp);
+    // Rounding up carries into the exponent, and past the largest half.
+    assert_eq!(f16_round_trip(2.0 - ulp / 4.0), 2.0);
+    assert_eq!(f16_round_trip(65520.0), f32::INFINITY);
+}
+
+#[test]
+fn test_f16_error_bounds() {
+    let mut v = 1e-9f32;
+    while v < 65504.0 {
+        for &v in &[v, -v] {
+            let error = (f16_round_trip(v) - v).abs();
+            if v.abs() >= 2f32.powi(-14) {
+                assert!(error <= v.abs() * 2f32.powi(-11), "{}", v);
+            } else {
+                assert!(error <= 2f32.powi(-25), "{}", v);
+            }
+        }
+        v *= 1.0137;
+    }
+}
+
+#[test]
+fn test_fixed() {
+    type Q = Fixed<i16, 64>;
+    assert_eq!(Q::MAX_SIZE, 2);
+    assert_eq!(poke(&Q::new(1.5)), poke(&96i16));
+    // Ties round away from zero.
+    assert_eq!(poke(&Q::new(1.0 / 128.0)), poke(&1i16));
+    assert_eq!(poke(&Q::new(-1.0 / 128.0)), poke(&-1i16));
+    // Out of range values saturate, and NaN is zero.
+    assert_eq!(poke(&Q::new(1e9)), poke(&i16::MAX));
+    assert_eq!(poke(&Q::new(-1e9)), poke(&i16::MIN));
+    assert_eq!(poke(&Q::new(f32::NAN)), poke(&0i16));
+
+    let mut v = -500.0f32;
+    while v < 500.0 {
+        let out = PeekReader::new(&poke(&Q::new(v))).read::<Q>().unwrap().0;
+        assert!((out - v).abs() <= 0.5 / 64.0, "{}", v);
+        v += 0.173;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Glyph {
+    #[peek_poke(f16)]
+    advance: f32,
+    #[peek_poke(fixed = u8, scale = 4)]
+    offset: [f32; 2],
+    size: f32,
+}
+
+#[test]
+fn test_derive_attributes() {
+    assert_eq!(Glyph::MAX_SIZE, 2 + 2 + 4);
+    let glyph = Glyph {
+        advance: 3.25,
+        offset: [1.25, 2.1],
+        size: 12.3,
+    };
+    let out = PeekReader::new(&poke(&glyph)).read::<Glyph>().unwrap();
+    assert_eq!(
+        out,
+        Glyph {
+            offset: [1.25, 2.0],
+            ..glyph
+        }
+    );
+}
+
+trait Pick {
+    type Out;
+}
+
+impl Pick for f32 {
+    type Out = f32;
+}
+
+#[test]
+fn test_derive_projection() {
+    // The encoding follows the field's type, not how it's spelled.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+    struct Scale {
+        #[peek_poke(f16)]
+        x: <f32 as Pick>::Out,
+    }
+
+    assert_eq!(Scale::MAX_SIZE, 2);
+    let scale = Scale { x: 1.5 };
+    assert_eq!(PeekReader::new(&poke(&scale)).read(), Ok(scale));
+}
+
+#[cfg(feature = "extras")]
+#[test]
+fn test_euclid() {
+    use euclid::default::{Point2D, Rect};
+
+    #[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+    enum Item {
+        #[default]
+        Empty,
+        Rect(#[peek_poke(fixed = i32, scale = 60)] Rect<f32>),
+    }
+
+    #[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+    struct Text {
+        #[peek_poke(f16)]
+        origin: Point2D<f32>,
+        item: Item,
+    }
+
+    assert_eq!(Point2D::<F16>::MAX_SIZE, 4);
+    assert_eq!(Text::MAX_SIZE, 4 + 1 + 16);
+    let text = Text {
+        origin: Point2D::new(0.5, -8.0),
+        item: Item::Rect(Rect::new(Point2D::new(1.5, 2.0), (10.0, 20.25).into())),
+    };
+    assert_eq!(PeekReader::new(&poke(&text)).read(), Ok(text));
+}
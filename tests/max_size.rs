@@ -10,8 +10,13 @@
 
 #![allow(dead_code)]
 
-use peek_poke::{PeekPoke, Poke};
-use std::{marker::PhantomData, mem::size_of};
+use peek_poke::{uninit_field, Peek, PeekPoke, Poke, TryPeek};
+use std::{
+    marker::PhantomData,
+    mem::{size_of, MaybeUninit},
+    num::{NonZeroIsize, NonZeroU64, NonZeroU8},
+    ptr,
+};
 
 #[test]
 fn test_numbers() {
@@ -35,6 +40,18 @@ fn test_bool() {
     assert_eq!(bool::MAX_SIZE, size_of::<u8>());
 }
 
+#[test]
+fn test_char() {
+    assert_eq!(char::MAX_SIZE, size_of::<u32>());
+}
+
+#[test]
+fn test_nonzero() {
+    assert_eq!(NonZeroU8::MAX_SIZE, size_of::<u8>());
+    assert_eq!(NonZeroU64::MAX_SIZE, size_of::<u64>());
+    assert_eq!(NonZeroIsize::MAX_SIZE, size_of::<isize>());
+}
+
 #[test]
 fn test_option() {
     assert_eq!(
@@ -44,6 +61,7 @@ fn test_option() {
 }
 
 #[test]
+#[allow(clippy::manual_bits)]
 fn test_fixed_size_array() {
     assert_eq!(<[u32; 32]>::MAX_SIZE, 32 * size_of::<u32>());
     assert_eq!(<[u64; 8]>::MAX_SIZE, 8 * size_of::<u64>());
@@ -51,6 +69,7 @@ fn test_fixed_size_array() {
 }
 
 #[test]
+#[allow(unused_parens)]
 fn test_tuple() {
     assert_eq!(<(isize)>::MAX_SIZE, size_of::<isize>());
     assert_eq!(<(isize, isize, isize)>::MAX_SIZE, 3 * size_of::<isize>());
@@ -98,7 +117,7 @@ fn test_enum_cstyle() {
         Inset = 8,
         Outset = 9,
     }
-    assert_eq!(BorderStyle::MAX_SIZE, <u8>::MAX_SIZE);
+    assert_eq!(BorderStyle::MAX_SIZE, <u32>::MAX_SIZE);
 }
 
 #[test]
@@ -119,3 +138,82 @@ fn test_const_max() {
     assert_eq!(peek_poke::max(2, 4), 4);
     assert_eq!(peek_poke::max(4, 4), 4);
 }
+
+/// A `Poke` implementation with a wrong `MAX_SIZE`.
+#[derive(Debug, Default, PartialEq)]
+struct TooSmall(u16);
+
+unsafe impl Poke for TooSmall {
+    const MAX_SIZE: usize = 1;
+
+    unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+        self.0.poke_into(bytes)
+    }
+}
+
+impl Peek for TooSmall {
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        u16::peek_from(
+            bytes,
+            uninit_field(ptr::addr_of_mut!((*output.as_mut_ptr()).0)),
+        )
+    }
+}
+
+impl TryPeek for TooSmall {}
+
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+struct ContainsTooSmall {
+    x: TooSmall,
+}
+
+#[test]
+#[cfg_attr(not(any(debug_assertions, feature = "check_max_size")), ignore)]
+#[should_panic(expected = "TooSmall::poke_into() used 2 bytes, but MAX_SIZE is 1")]
+fn test_check_max_size_helper() {
+    let mut v = Vec::new();
+    peek_poke::poke_into_vec(&TooSmall(0), &mut v);
+}
+
+#[test]
+#[cfg_attr(not(any(debug_assertions, feature = "check_max_size")), ignore)]
+#[should_panic(expected = "ContainsTooSmall::poke_into() used 2 bytes, but MAX_SIZE is 1")]
+fn test_check_max_size_derive_poke() {
+    let mut buf = [0u8; 8];
+    unsafe {
+        ContainsTooSmall::default().poke_into(buf.as_mut_ptr());
+    }
+}
+
+#[test]
+#[cfg_attr(not(any(debug_assertions, feature = "check_max_size")), ignore)]
+#[should_panic(expected = "ContainsTooSmall::peek_from() used 2 bytes, but MAX_SIZE is 1")]
+fn test_check_max_size_derive_peek() {
+    let buf = [0u8; 8];
+    unsafe {
+        peek_poke::peek_from_default::<ContainsTooSmall>(buf.as_ptr());
+    }
+}
+
+#[derive(Default, PeekPoke)]
+#[peek_poke(max_size = 14)]
+struct AtBudget {
+    a: u32,
+    b: u64,
+    c: Option<u8>,
+}
+
+#[derive(Default, PeekPoke)]
+#[peek_poke(max_size = 5)]
+enum EnumAtBudget {
+    #[default]
+    A,
+    B(u32),
+}
+
+#[test]
+fn test_max_size_budget() {
+    // Exceeding the budget is a compile error, see the crate docs.
+    assert_eq!(AtBudget::MAX_SIZE, 14);
+    assert_eq!(EnumAtBudget::MAX_SIZE, 5);
+}
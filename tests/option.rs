@@ -0,0 +1,118 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+mod common;
+
+use common::poke;
+use peek_poke::{PeekError, PeekPoke, PeekReader, Poke};
+use std::num::{NonZeroI16, NonZeroU32};
+
+#[test]
+fn test_nonzero_niche() {
+    assert_eq!(Option::<NonZeroU32>::MAX_SIZE, 4);
+    assert_eq!(poke(&None::<NonZeroU32>), [0; 4]);
+
+    let some = NonZeroU32::new(7);
+    assert_eq!(poke(&some), poke(&7u32));
+    assert_eq!(PeekReader::new(&poke(&some)).read(), Ok(some));
+    assert_eq!(
+        PeekReader::new(&poke(&None::<NonZeroU32>)).read::<Option<NonZeroU32>>(),
+        Ok(None)
+    );
+
+    let neg = NonZeroI16::new(-1);
+    assert_eq!(PeekReader::new(&poke(&neg)).read(), Ok(neg));
+}
+
+#[test]
+fn test_nonzero_niche_unexpected_end() {
+    assert_eq!(
+        PeekReader::new(&[0, 0]).read::<Option<NonZeroU32>>(),
+        Err(PeekError::UnexpectedEnd)
+    );
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+enum ItemTag {
+    #[default]
+    A,
+    B(u16),
+}
+
+#[test]
+fn test_enum_niche() {
+    assert_eq!(Option::<ItemTag>::MAX_SIZE, ItemTag::MAX_SIZE);
+    assert_eq!(poke(&None::<ItemTag>), [u8::MAX]);
+    assert_eq!(poke(&Some(ItemTag::A)), [0]);
+    assert_eq!(poke(&Some(ItemTag::B(1))), poke(&ItemTag::B(1)));
+    for &v in &[None, Some(ItemTag::A), Some(ItemTag::B(0x1234))] {
+        assert_eq!(PeekReader::new(&poke(&v)).read(), Ok(v));
+    }
+    // `Option<Option<_>>` can't use the niche twice.
+    let nested = Some(None::<ItemTag>);
+    assert_eq!(poke(&nested), [1, u8::MAX]);
+    assert_eq!(PeekReader::new(&poke(&nested)).read(), Ok(nested));
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+#[repr(u8)]
+enum UsesMax {
+    #[default]
+    A = 0,
+    B = 255,
+}
+
+#[test]
+fn test_enum_niche_taken() {
+    assert_eq!(Option::<UsesMax>::MAX_SIZE, 1 + UsesMax::MAX_SIZE);
+    assert_eq!(poke(&None::<UsesMax>), [0]);
+    assert_eq!(poke(&Some(UsesMax::B)), [1, 255]);
+    for &v in &[None, Some(UsesMax::A), Some(UsesMax::B)] {
+        assert_eq!(PeekReader::new(&poke(&v)).read(), Ok(v));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+enum WithOther {
+    #[default]
+    A,
+    #[peek_poke(other)]
+    Unknown(u8),
+}
+
+#[test]
+fn test_enum_other_has_no_niche() {
+    assert_eq!(Option::<WithOther>::MAX_SIZE, 1 + WithOther::MAX_SIZE);
+    assert_eq!(poke(&None::<WithOther>), [0]);
+    let unknown = Some(WithOther::Unknown(u8::MAX));
+    assert_eq!(PeekReader::new(&poke(&unknown)).read(), Ok(unknown));
+}
+
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+struct DisplayItem {
+    hit_info: Option<(u64, u16)>,
+    tag: Option<ItemTag>,
+    id: Option<NonZeroU32>,
+}
+
+#[test]
+fn test_niche_fields() {
+    assert_eq!(DisplayItem::MAX_SIZE, 1 + 10 + 3 + 4);
+    let item = DisplayItem {
+        hit_info: Some((1, 2)),
+        tag: Some(ItemTag::B(3)),
+        id: NonZeroU32::new(4),
+    };
+    assert_eq!(PeekReader::new(&poke(&item)).read(), Ok(item));
+    assert_eq!(
+        PeekReader::new(&poke(&DisplayItem::default())).read(),
+        Ok(DisplayItem::default())
+    );
+}
@@ -0,0 +1,113 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use peek_poke::{PeekError, PeekReader, Poke};
+
+/// A buffer aligned for any primitive, so that offsets decide alignment.
+#[repr(C, align(16))]
+struct Buf([u8; 128]);
+
+/// Serialize `values` into a `Buf` starting at `offset`.
+fn poke_at<T: Poke>(offset: usize, values: &[T]) -> Buf {
+    let mut bytes = Vec::new();
+    peek_poke::poke_extend_vec(values.iter(), &mut bytes);
+    let mut buf = Buf([0; 128]);
+    buf.0[offset..offset + bytes.len()].copy_from_slice(&bytes);
+    buf
+}
+
+#[test]
+fn test_read_ref_aligned() {
+    let buf = poke_at(0, &[0x1234_5678u32, 9]);
+    let mut reader = PeekReader::new(&buf.0);
+    let v = reader.read_ref::<u32>().unwrap();
+    assert!(v.is_borrowed());
+    assert_eq!(&*v as *const u32 as *const u8, buf.0.as_ptr());
+    assert_eq!(*v, 0x1234_5678);
+    assert_eq!(*reader.read_ref::<u32>().unwrap(), 9);
+    assert_eq!(reader.position(), 8);
+}
+
+#[test]
+fn test_read_ref_unaligned() {
+    let buf = poke_at(1, &[0x1234_5678u32]);
+    let mut reader = PeekReader::new(&buf.0[1..]);
+    let v = reader.read_ref::<u32>().unwrap();
+    assert!(!v.is_borrowed());
+    assert_eq!(*v, 0x1234_5678);
+    assert_eq!(v.into_owned(), 0x1234_5678);
+    assert_eq!(reader.position(), 4);
+}
+
+#[test]
+fn test_read_slice_ref() {
+    let floats = [1.5f32, -2.0, 3.25, 4.0];
+    for &offset in &[0, 2] {
+        let buf = poke_at(offset, &floats);
+        let mut reader = PeekReader::new(&buf.0[offset..]);
+        let v = reader.read_slice_ref::<f32>(4).unwrap();
+        assert_eq!(v.is_borrowed(), offset == 0);
+        assert_eq!(*v, floats);
+        assert_eq!(v.into_owned(), floats.to_vec());
+        assert_eq!(reader.position(), 16);
+    }
+}
+
+#[test]
+fn test_read_ref_array() {
+    let rows = [[1u16, 2, 3], [4, 5, 6]];
+    let buf = poke_at(0, &rows);
+    let mut reader = PeekReader::new(&buf.0);
+    assert_eq!(*reader.read_ref::<[u16; 3]>().unwrap(), rows[0]);
+    assert_eq!(*reader.read_slice_ref::<[u16; 3]>(1).unwrap(), rows[1..]);
+    assert_eq!(reader.position(), 12);
+}
+
+#[test]
+fn test_read_ref_truncated() {
+    let buf = poke_at(0, &[1u64]);
+    let mut reader = PeekReader::new(&buf.0[..7]);
+    assert_eq!(
+        reader.read_ref::<u64>().unwrap_err(),
+        PeekError::UnexpectedEnd
+    );
+    assert_eq!(
+        reader.read_slice_ref::<u8>(8).unwrap_err(),
+        PeekError::UnexpectedEnd
+    );
+    assert_eq!(
+        reader.read_slice_ref::<u64>(usize::MAX).unwrap_err(),
+        PeekError::UnexpectedEnd
+    );
+    assert_eq!(reader.position(), 0);
+    assert_eq!(*reader.read_slice_ref::<u8>(7).unwrap(), buf.0[..7]);
+}
+
+#[cfg(feature = "extras")]
+#[test]
+fn test_read_ref_euclid() {
+    use euclid::default::{Point2D, Transform3D};
+
+    let transforms = [
+        Transform3D::<f32>::create_translation(1.0, 2.0, 3.0),
+        Transform3D::create_scale(4.0, 5.0, 6.0),
+    ];
+    let buf = poke_at(0, &transforms);
+    let mut reader = PeekReader::new(&buf.0);
+    let v = reader.read_slice_ref::<Transform3D<f32>>(2).unwrap();
+    assert!(v.is_borrowed());
+    assert_eq!(*v, transforms);
+
+    let buf = poke_at(4, &[Point2D::new(1.0f64, 2.0)]);
+    let mut reader = PeekReader::new(&buf.0[4..]);
+    let v = reader.read_ref::<Point2D<f64>>().unwrap();
+    assert!(!v.is_borrowed());
+    assert_eq!(*v, Point2D::new(1.0, 2.0));
+}
@@ -0,0 +1,55 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use peek_poke::{PeekError, PeekPoke, PeekReader};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Bar {
+    a: u32,
+    b: Option<u64>,
+}
+
+#[test]
+fn test_read_sequence() {
+    let mut bytes = Vec::new();
+    peek_poke::poke_into_vec(&1u8, &mut bytes);
+    peek_poke::poke_into_vec(&Bar { a: 2, b: None }, &mut bytes);
+    peek_poke::poke_into_vec(&Bar { a: 3, b: Some(4) }, &mut bytes);
+
+    let mut reader = PeekReader::new(&bytes);
+    assert_eq!(reader.remaining(), bytes.len());
+    assert_eq!(reader.read::<u8>(), Ok(1));
+    assert_eq!(reader.position(), 1);
+    // `None` is shorter than `Bar::MAX_SIZE`, so this relies on the reader
+    // not requiring a red zone.
+    assert_eq!(reader.read::<Bar>(), Ok(Bar { a: 2, b: None }));
+    assert_eq!(reader.position(), 6);
+
+    let mut bar = Bar::default();
+    assert_eq!(reader.read_into(&mut bar), Ok(()));
+    assert_eq!(bar, Bar { a: 3, b: Some(4) });
+    assert!(reader.is_empty());
+    assert_eq!(reader.remaining(), 0);
+    assert_eq!(reader.position(), bytes.len());
+}
+
+#[test]
+fn test_error_does_not_advance() {
+    let mut bytes = Vec::new();
+    peek_poke::poke_into_vec(&1u16, &mut bytes);
+    peek_poke::poke_into_vec(&2u8, &mut bytes);
+
+    let mut reader = PeekReader::new(&bytes);
+    assert_eq!(reader.read::<u16>(), Ok(1));
+    assert_eq!(reader.read::<u32>(), Err(PeekError::UnexpectedEnd));
+    assert_eq!(reader.position(), 2);
+    assert_eq!(reader.read::<u8>(), Ok(2));
+    assert_eq!(reader.read::<u8>(), Err(PeekError::UnexpectedEnd));
+}
@@ -22,26 +22,12 @@ fn poke_into<V: Peek + Poke>(a: &V) -> Vec<u8> {
     v
 }
 
-#[cfg(not(feature = "option_copy"))]
 fn the_same<V>(a: V)
 where
-    V: Debug + Default + PartialEq + Peek + Poke,
+    V: Debug + PartialEq + Peek + Poke,
 {
     let v = poke_into(&a);
-    let (b, end_ptr) = unsafe { peek_poke::peek_from_default(v.as_ptr()) };
-    let size = end_ptr as usize - v.as_ptr() as usize;
-    assert_eq!(size, v.len());
-    assert_eq!(a, b);
-}
-
-#[cfg(feature = "option_copy")]
-fn the_same<V>(a: V)
-where
-    V: Copy + Debug + PartialEq + Peek + Poke,
-{
-    let v = poke_into(&a);
-    let mut b = a;
-    let end_ptr = unsafe { b.peek_from(v.as_ptr()) };
+    let (b, end_ptr) = unsafe { peek_poke::peek_from_uninit(v.as_ptr()) };
     let size = end_ptr as usize - v.as_ptr() as usize;
     assert_eq!(size, v.len());
     assert_eq!(a, b);
@@ -81,12 +67,49 @@ fn test_bool() {
     the_same(false);
 }
 
-#[cfg(any(feature = "option_copy", feature = "option_default"))]
+#[test]
+fn test_char() {
+    the_same('a');
+    the_same('\u{1f980}');
+}
+
 #[test]
 fn test_option() {
     the_same(Some(5usize));
-    //the_same(Some("foo bar".to_string()));
     the_same(None::<usize>);
+    the_same(Some(Some(5u8)));
+    the_same(Some(None::<u8>));
+}
+
+#[test]
+fn test_no_default() {
+    #[derive(Clone, Copy, Debug, PartialEq, PeekPoke)]
+    enum ClipId {
+        Clip(usize),
+        ClipChain(u64),
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, PeekPoke)]
+    struct Item {
+        clip_id: ClipId,
+        parent: Option<ClipId>,
+        #[peek_poke(varint)]
+        id: u32,
+    }
+
+    the_same(ClipId::ClipChain(3));
+    the_same([ClipId::Clip(1), ClipId::ClipChain(2)]);
+    the_same((ClipId::Clip(1), 2u8));
+    the_same(Item {
+        clip_id: ClipId::Clip(4),
+        parent: Some(ClipId::ClipChain(5)),
+        id: 300,
+    });
+    the_same(Item {
+        clip_id: ClipId::Clip(4),
+        parent: None,
+        id: 6,
+    });
 }
 
 #[test]
@@ -110,7 +133,6 @@ fn test_basic_struct() {
         a: u32,
         b: u32,
         c: u32,
-        #[cfg(any(feature = "option_copy", feature = "option_default"))]
         d: Option<u32>,
     }
 
@@ -118,12 +140,18 @@ fn test_basic_struct() {
         a: 2,
         b: 4,
         c: 42,
-        #[cfg(any(feature = "option_copy", feature = "option_default"))]
         d: None,
     });
+    the_same(Bar {
+        a: 2,
+        b: 4,
+        c: 42,
+        d: Some(7),
+    });
 }
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn test_enum() {
     #[derive(Clone, Copy, Debug, PartialEq, PeekPoke)]
     enum TestEnum {
@@ -134,6 +162,7 @@ fn test_enum() {
         StructLike { x: usize, y: f32 },
     }
 
+    #[allow(clippy::derivable_impls)]
     impl Default for TestEnum {
         fn default() -> Self {
             TestEnum::NoArg
@@ -164,6 +193,7 @@ fn test_enum_cstyle() {
         Outset = 9,
     }
 
+    #[allow(clippy::derivable_impls)]
     impl Default for BorderStyle {
         fn default() -> Self {
             BorderStyle::None
@@ -222,11 +252,14 @@ fn test_generic_enum() {
         Binding(PropertyBindingKey<T>, T),
     }
 
-    impl<T: Default> Default for PropertyBinding<T> {
-        fn default() -> Self {
-            PropertyBinding::Value(Default::default())
-        }
-    }
+    the_same(PropertyBinding::Value(42.0f32));
+    the_same(PropertyBinding::Binding(
+        PropertyBindingKey {
+            id: 19,
+            _phantom: PhantomData,
+        },
+        42.0f32,
+    ));
 }
 
 #[cfg(feature = "extras")]
@@ -0,0 +1,197 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![allow(clippy::assertions_on_constants)]
+
+mod common;
+
+use common::poke;
+use peek_poke::{PeekPoke, PeekReader, Poke};
+use std::{marker::PhantomData, rc::Rc};
+
+fn unknown_bounds() -> Option<[f32; 4]> {
+    Some([0.0; 4])
+}
+
+/// A handle that's only meaningful in the process that interned it, and
+/// doesn't implement `Poke`.
+#[derive(Debug, Default, PartialEq)]
+struct Interned(Rc<str>);
+
+#[derive(Debug, PartialEq, PeekPoke)]
+struct Item {
+    id: u32,
+    #[peek_poke(skip)]
+    handle: Interned,
+    #[peek_poke(skip, default = "unknown_bounds")]
+    bounds: Option<[f32; 4]>,
+    visible: bool,
+}
+
+#[test]
+fn test_struct() {
+    assert_eq!(Item::MAX_SIZE, 4 + 1);
+    let item = Item {
+        id: 7,
+        handle: Interned("clip".into()),
+        bounds: None,
+        visible: true,
+    };
+    let bytes = poke(&item);
+    assert_eq!(bytes, poke(&(7u32, true)));
+
+    let expected = Item {
+        id: 7,
+        handle: Interned::default(),
+        bounds: Some([0.0; 4]),
+        visible: true,
+    };
+    let mut padded = bytes.clone();
+    peek_poke::ensure_red_zone::<Item>(&mut padded);
+    let (peeked, _) = unsafe { peek_poke::peek_from_uninit::<Item>(padded.as_ptr()) };
+    assert_eq!(peeked, expected);
+    assert_eq!(PeekReader::new(&bytes).read::<Item>(), Ok(expected));
+}
+
+#[derive(Debug, PartialEq, PeekPoke)]
+enum Op {
+    Push(u8, #[peek_poke(skip)] Interned),
+    Pop {
+        count: u16,
+        #[peek_poke(skip, default = "unknown_bounds")]
+        bounds: Option<[f32; 4]>,
+    },
+}
+
+#[test]
+fn test_enum() {
+    assert_eq!(Op::MAX_SIZE, 1 + 2);
+    let bytes = poke(&Op::Push(3, Interned("a".into())));
+    assert_eq!(bytes, [0, 3]);
+    assert_eq!(
+        PeekReader::new(&bytes).read::<Op>(),
+        Ok(Op::Push(3, Interned::default()))
+    );
+
+    let bytes = poke(&Op::Pop {
+        count: 2,
+        bounds: None,
+    });
+    assert_eq!(bytes.len(), 3);
+    assert_eq!(
+        PeekReader::new(&bytes).read::<Op>(),
+        Ok(Op::Pop {
+            count: 2,
+            bounds: Some([0.0; 4]),
+        })
+    );
+}
+
+#[derive(Debug, PartialEq, PeekPoke)]
+struct Flags {
+    a: bool,
+    #[peek_poke(skip)]
+    dirty: bool,
+    b: bool,
+}
+
+#[test]
+fn test_bits_pack_across_skipped_fields() {
+    let flags = Flags {
+        a: true,
+        dirty: true,
+        b: true,
+    };
+    assert_eq!(Flags::MAX_SIZE, 1);
+    let bytes = poke(&flags);
+    assert_eq!(bytes, [0b11]);
+    assert_eq!(
+        PeekReader::new(&bytes).read::<Flags>(),
+        Ok(Flags {
+            dirty: false,
+            ..flags
+        })
+    );
+}
+
+/// `T` is only used by a skipped field, so needn't implement `Poke`.
+#[derive(Debug, PartialEq, PeekPoke)]
+struct Tagged<T> {
+    value: u32,
+    #[peek_poke(skip)]
+    _marker: PhantomData<Vec<T>>,
+}
+
+#[test]
+fn test_generic_skipped_field() {
+    struct NotPoke;
+    let value = Tagged::<NotPoke> {
+        value: 5,
+        _marker: PhantomData,
+    };
+    let bytes = poke(&value);
+    assert_eq!(bytes, poke(&5u32));
+    assert_eq!(
+        PeekReader::new(&bytes)
+            .read::<Tagged<NotPoke>>()
+            .map(|t| t.value),
+        Ok(5)
+    );
+}
+
+/// A skipped field of a generic type is peeked as `T::default()`.
+#[derive(Debug, PartialEq, PeekPoke)]
+struct Cached<T> {
+    a: u32,
+    #[peek_poke(skip)]
+    cache: T,
+}
+
+#[test]
+fn test_generic_default_field() {
+    let value = Cached {
+        a: 3,
+        cache: String::from("three"),
+    };
+    let bytes = poke(&value);
+    assert_eq!(bytes, poke(&3u32));
+    assert_eq!(
+        PeekReader::new(&bytes).read::<Cached<String>>(),
+        Ok(Cached {
+            a: 3,
+            cache: String::new(),
+        })
+    );
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, PeekPoke)]
+struct Vertex {
+    pos: [f32; 2],
+    #[peek_poke(skip)]
+    cached: u32,
+}
+
+#[test]
+fn test_not_verbatim() {
+    assert!(!Vertex::VERBATIM);
+    let bytes = poke(&Vertex {
+        pos: [1.0, 2.0],
+        cached: 9,
+    });
+    assert_eq!(bytes.len(), 8);
+    assert_eq!(
+        PeekReader::new(&bytes).read::<Vertex>(),
+        Ok(Vertex {
+            pos: [1.0, 2.0],
+            cached: 0,
+        })
+    );
+}
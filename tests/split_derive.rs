@@ -0,0 +1,188 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+mod common;
+
+use common::poke;
+use peek_poke::{
+    GpuLayout, GpuVec, Peek, PeekCopy, PeekDefault, PeekPoke, PeekReader, Poke, Std140, TryPeek,
+};
+use std::mem::MaybeUninit;
+
+#[derive(Clone, Copy, Debug, PartialEq, PeekPoke)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Debug, PartialEq, PeekPoke)]
+struct Item {
+    id: u32,
+    point: Point,
+    parent: Option<u16>,
+}
+
+#[derive(Poke)]
+struct ItemRef<'a> {
+    id: u32,
+    point: &'a Point,
+    parent: Option<&'a u16>,
+}
+
+#[derive(Poke)]
+enum Shape<'a, T> {
+    Line(&'a T, &'a T),
+    Empty,
+}
+
+#[test]
+fn test_poke_only_struct() {
+    let point = Point { x: 1.0, y: 2.0 };
+    let parent = 7;
+    let bytes = poke(&ItemRef {
+        id: 3,
+        point: &point,
+        parent: Some(&parent),
+    });
+    assert_eq!(ItemRef::MAX_SIZE, Item::MAX_SIZE);
+    assert_eq!(
+        PeekReader::new(&bytes).read::<Item>(),
+        Ok(Item {
+            id: 3,
+            point,
+            parent: Some(7),
+        })
+    );
+}
+
+#[test]
+fn test_poke_only_generic_enum() {
+    let (a, b) = (Point { x: 1.0, y: 2.0 }, Point { x: 3.0, y: 4.0 });
+    assert_eq!(Shape::<Point>::MAX_SIZE, 1 + 2 * Point::MAX_SIZE);
+    let bytes = poke(&Shape::Line(&a, &b));
+    assert_eq!(bytes[0], 0);
+    assert_eq!(bytes[1..], poke(&(a, b))[..]);
+    assert_eq!(poke(&Shape::<Point>::Empty), [1]);
+}
+
+/// Serialized as its value in tenths, with a hand-written `Poke` and derived
+/// `Peek`.
+#[derive(Debug, PartialEq, Peek)]
+struct Tenths(u16);
+
+unsafe impl Poke for Tenths {
+    const MAX_SIZE: usize = u16::MAX_SIZE;
+
+    unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+        self.0.poke_into(bytes)
+    }
+}
+
+#[test]
+fn test_peek_only() {
+    let bytes = poke(&[Tenths(5), Tenths(300)]);
+    let mut reader = PeekReader::new(&bytes);
+    assert_eq!(reader.read::<Tenths>(), Ok(Tenths(5)));
+    assert_eq!(reader.read::<Tenths>(), Ok(Tenths(300)));
+    assert!(reader.is_empty());
+    assert!(PeekReader::new(&bytes[..1]).read::<Tenths>().is_err());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Poke, Peek)]
+enum Side {
+    Left,
+    Right,
+}
+
+#[test]
+fn test_both_derives() {
+    // `Poke` declares the niche, and `Peek` decodes it.
+    assert_eq!(Option::<Side>::MAX_SIZE, 1);
+    for value in [None, Some(Side::Left), Some(Side::Right)] {
+        let bytes = poke(&value);
+        assert_eq!(bytes.len(), 1);
+        assert_eq!(PeekReader::new(&bytes).read::<Option<Side>>(), Ok(value));
+    }
+    fn is_try_peek<T: TryPeek>() {}
+    is_try_peek::<Side>();
+}
+
+/// With `layout`, `#[derive(Poke)]` implements `GpuLayout`, which a
+/// hand-written `Peek` can read with.
+#[derive(Clone, Copy, Debug, PartialEq, Poke)]
+#[peek_poke(layout = std140)]
+struct Light {
+    intensity: f32,
+    position: GpuVec<f32, 3>,
+}
+
+impl Peek for Light {
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        <Self as GpuLayout<Std140>>::peek_gpu(bytes, output);
+        bytes.add(<Self as GpuLayout<Std140>>::SIZE)
+    }
+}
+
+impl TryPeek for Light {}
+
+#[test]
+fn test_poke_only_layout() {
+    assert_eq!(<Light as GpuLayout<Std140>>::SIZE, 32);
+    let light = Light {
+        intensity: 0.5,
+        position: GpuVec([1.0, 2.0, 3.0]),
+    };
+    let bytes = poke(&light);
+    assert_eq!(bytes.len(), 32);
+    assert_eq!(PeekReader::new(&bytes).read::<Light>(), Ok(light));
+}
+
+/// A hand-written `Poke`, with `Peek` derived by the derives that check for
+/// `Copy` and `Default`.
+#[derive(Clone, Copy, Debug, PartialEq, PeekCopy)]
+struct Celsius(i16);
+
+unsafe impl Poke for Celsius {
+    const MAX_SIZE: usize = i16::MAX_SIZE;
+
+    unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+        self.0.poke_into(bytes)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, PeekDefault)]
+struct Reading<T> {
+    value: T,
+    label: Option<u8>,
+}
+
+unsafe impl<T: Poke> Poke for Reading<T> {
+    const MAX_SIZE: usize = T::MAX_SIZE + Option::<u8>::MAX_SIZE;
+
+    unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+        let bytes = self.value.poke_into(bytes);
+        self.label.poke_into(bytes)
+    }
+}
+
+#[test]
+fn test_peek_copy_and_default() {
+    let reading = Reading {
+        value: Celsius(-40),
+        label: Some(3),
+    };
+    let bytes = poke(&reading);
+    assert_eq!(bytes.len(), 2 + 2);
+    assert_eq!(PeekReader::new(&bytes).read(), Ok(reading));
+    assert_eq!(
+        PeekReader::new(&poke(&Celsius(21))).read::<Celsius>(),
+        Ok(Celsius(21))
+    );
+}
@@ -0,0 +1,97 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![cfg(feature = "testing")]
+
+use peek_poke::{assert_encoding, testing, uninit_field, Peek, PeekPoke, Poke, TryPeek};
+use std::{mem::MaybeUninit, ptr};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+enum Baz {
+    #[default]
+    NoArg,
+    OneArg(u8),
+    Flags {
+        a: bool,
+        b: Option<u8>,
+    },
+}
+
+/// A `Poke` implementation with a wrong `MAX_SIZE`.
+#[derive(Debug, Default, PartialEq)]
+struct TooSmall(u16);
+
+unsafe impl Poke for TooSmall {
+    const MAX_SIZE: usize = 1;
+
+    unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+        self.0.poke_into(bytes)
+    }
+}
+
+impl Peek for TooSmall {
+    unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+        u16::peek_from(
+            bytes,
+            uninit_field(ptr::addr_of_mut!((*output.as_mut_ptr()).0)),
+        )
+    }
+}
+
+impl TryPeek for TooSmall {}
+
+#[test]
+fn test_round_trip() {
+    testing::assert_round_trip(&5u32);
+    testing::assert_round_trip(&Some(-1i64));
+    testing::assert_round_trip(&Baz::NoArg);
+    testing::assert_round_trip(&Baz::Flags {
+        a: true,
+        b: Some(3),
+    });
+}
+
+#[test]
+fn test_max_size_honored() {
+    testing::assert_max_size_honored(&[7u64; 4]);
+    testing::assert_max_size_honored(&Baz::OneArg(0xa5));
+}
+
+#[test]
+#[should_panic(expected = "poke_into() wrote 1 bytes past MAX_SIZE (1)")]
+fn test_max_size_violated() {
+    testing::assert_max_size_honored(&TooSmall(0x1234));
+}
+
+#[test]
+#[should_panic(expected = "TooSmall: poke_into() wrote 1 bytes past MAX_SIZE")]
+fn test_round_trip_checks_max_size() {
+    testing::assert_round_trip(&TooSmall(0xa5a5));
+}
+
+#[test]
+fn test_encoding() {
+    assert_encoding!(Baz::NoArg, [0]);
+    assert_encoding!(Baz::OneArg(9), [1, 9]);
+    assert_encoding!(Baz::Flags { a: true, b: None }, [2, 1, 0]);
+    assert_encoding!(
+        Baz::Flags {
+            a: false,
+            b: Some(4)
+        },
+        vec![2, 0, 1, 4]
+    );
+}
+
+#[test]
+#[should_panic(expected = "encoding doesn't match")]
+fn test_encoding_mismatch() {
+    assert_encoding!(Baz::OneArg(9), [1, 8]);
+}
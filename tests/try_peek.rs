@@ -0,0 +1,198 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use peek_poke::{uninit_field, Peek, PeekError, PeekPoke, Poke, TryPeek};
+use std::{fmt::Debug, mem::MaybeUninit, num::NonZeroU16, ptr};
+
+fn poke_into<V: Poke>(a: &V) -> Vec<u8> {
+    let mut v = Vec::new();
+    peek_poke::poke_into_vec(a, &mut v);
+    v
+}
+
+fn try_the_same<V>(a: V)
+where
+    V: Debug + Default + PartialEq + TryPeek,
+{
+    // No red zone: `try_peek_from_slice` must only read what was written.
+    let v = poke_into(&a);
+    let mut b = V::default();
+    let rest = peek_poke::try_peek_from_slice(&v, &mut b).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(a, b);
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Bar {
+    a: u32,
+    b: Option<u16>,
+    c: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+enum Baz {
+    #[default]
+    NoArg,
+    OneArg(u64),
+    StructLike {
+        x: u8,
+        y: Option<u8>,
+    },
+}
+
+#[test]
+fn test_round_trip() {
+    try_the_same(5u8);
+    try_the_same(-5i64);
+    try_the_same(1.5f32);
+    try_the_same(true);
+    try_the_same('\u{1f980}');
+    try_the_same(Some(5u32));
+    try_the_same(None::<u32>);
+    try_the_same([1u16, 2, 3]);
+    try_the_same((1u8, 2i32, ()));
+    try_the_same(Bar {
+        a: 42,
+        b: Some(7),
+        c: true,
+    });
+    try_the_same(Baz::NoArg);
+    try_the_same(Baz::OneArg(19));
+    try_the_same(Baz::StructLike { x: 4, y: None });
+}
+
+#[test]
+fn test_truncated() {
+    let v = poke_into(&0x1234_5678u32);
+    let mut out = 0u32;
+    assert_eq!(
+        peek_poke::try_peek_from_slice(&v[..3], &mut out),
+        Err(PeekError::UnexpectedEnd)
+    );
+
+    let v = poke_into(&Baz::OneArg(19));
+    for len in 0..v.len() {
+        let mut out = Baz::default();
+        assert_eq!(
+            peek_poke::try_peek_from_slice(&v[..len], &mut out),
+            Err(PeekError::UnexpectedEnd)
+        );
+    }
+}
+
+#[test]
+fn test_invalid_enum_tag() {
+    let mut out = Baz::default();
+    assert_eq!(
+        peek_poke::try_peek_from_slice(&[3u8, 0, 0], &mut out),
+        Err(PeekError::InvalidTag {
+            type_name: "Baz",
+            tag: 3
+        })
+    );
+}
+
+#[test]
+fn test_invalid_option_tag() {
+    let mut out = None::<u8>;
+    assert_eq!(
+        peek_poke::try_peek_from_slice(&[2u8, 0], &mut out),
+        Err(PeekError::InvalidOptionTag(2))
+    );
+
+    // The error propagates out of nested fields.
+    let mut v = poke_into(&Bar::default());
+    v[4] = 0xff;
+    let mut out = Bar::default();
+    assert_eq!(
+        peek_poke::try_peek_from_slice(&v, &mut out),
+        Err(PeekError::InvalidOptionTag(0xff))
+    );
+}
+
+#[test]
+fn test_provided_try_peek_from() {
+    #[derive(Debug, Default, PartialEq)]
+    struct HandWritten(u16);
+
+    unsafe impl Poke for HandWritten {
+        const MAX_SIZE: usize = 4;
+
+        unsafe fn poke_into(&self, bytes: *mut u8) -> *mut u8 {
+            self.0.poke_into(bytes)
+        }
+    }
+
+    impl Peek for HandWritten {
+        unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Self>) -> *const u8 {
+            u16::peek_from(
+                bytes,
+                uninit_field(ptr::addr_of_mut!((*output.as_mut_ptr()).0)),
+            )
+        }
+    }
+
+    impl TryPeek for HandWritten {}
+
+    // Fewer than `MAX_SIZE` bytes, but enough for the value that was written.
+    try_the_same(HandWritten(0xbeef));
+
+    let mut out = HandWritten::default();
+    assert_eq!(
+        peek_poke::try_peek_from_slice(&[1], &mut out),
+        Err(PeekError::UnexpectedEnd)
+    );
+}
+
+#[test]
+fn test_invalid_char() {
+    let v = poke_into(&0xd800u32);
+    let mut out = 'a';
+    assert_eq!(
+        peek_poke::try_peek_from_slice(&v, &mut out),
+        Err(PeekError::InvalidValue { type_name: "char" })
+    );
+}
+
+#[test]
+fn test_nonzero() {
+    let mut out = NonZeroU16::new(1).unwrap();
+    let v = poke_into(&NonZeroU16::new(300).unwrap());
+    assert_eq!(peek_poke::try_peek_from_slice(&v, &mut out), Ok(&[][..]));
+    assert_eq!(out.get(), 300);
+
+    let v = poke_into(&0u16);
+    assert_eq!(
+        peek_poke::try_peek_from_slice(&v, &mut out),
+        Err(PeekError::InvalidValue {
+            type_name: "NonZeroU16"
+        })
+    );
+}
+
+#[test]
+fn test_bool() {
+    let mut out = false;
+    assert_eq!(peek_poke::try_peek_from_slice(&[1], &mut out), Ok(&[][..]));
+    assert!(out);
+
+    // Derived types pack `bool` fields into bits, and the strictness extends
+    // to the unused bits.
+    let mut v = poke_into(&Bar::default());
+    *v.last_mut().unwrap() = 3;
+    let mut bar = Bar::default();
+    let res = peek_poke::try_peek_from_slice(&v, &mut bar);
+    if cfg!(feature = "strict") {
+        assert_eq!(res, Err(PeekError::InvalidValue { type_name: "Bar" }));
+    } else {
+        assert_eq!(res, Ok(&[][..]));
+        assert!(bar.c);
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use peek_poke::{Poke, PokeError};
+
+#[test]
+fn test_slice() {
+    let mut buf = [0xffu8; 8];
+    assert_eq!(
+        peek_poke::try_poke_into_slice(&0x0102_0304u32, &mut buf),
+        Ok(4)
+    );
+    assert_eq!(&buf[..4], &0x0102_0304u32.to_ne_bytes());
+    assert_eq!(&buf[4..], &[0xff; 4]);
+}
+
+#[test]
+fn test_slice_smaller_than_max_size() {
+    // `None` only writes its tag, so it fits even though `MAX_SIZE` doesn't.
+    assert_eq!(Option::<u64>::MAX_SIZE, 9);
+    let mut buf = [0xffu8; 2];
+    assert_eq!(
+        peek_poke::try_poke_into_slice(&None::<u64>, &mut buf),
+        Ok(1)
+    );
+    assert_eq!(buf, [0, 0xff]);
+}
+
+#[test]
+fn test_slice_smaller_than_large_max_size() {
+    // Too big to serialize on the stack, so the temporary buffer is allocated.
+    assert_eq!(Option::<[[u64; 32]; 2]>::MAX_SIZE, 513);
+    let mut buf = [0xffu8; 2];
+    assert_eq!(
+        peek_poke::try_poke_into_slice(&None::<[[u64; 32]; 2]>, &mut buf),
+        Ok(1)
+    );
+    assert_eq!(buf, [0, 0xff]);
+    assert_eq!(
+        peek_poke::try_poke_into_slice(&Some([[0u64; 32]; 2]), &mut buf),
+        Err(PokeError::BufferTooSmall {
+            needed: 513,
+            available: 2
+        })
+    );
+}
+
+#[test]
+fn test_slice_too_small() {
+    let mut buf = [0xffu8; 8];
+    assert_eq!(
+        peek_poke::try_poke_into_slice(&Some(5u64), &mut buf),
+        Err(PokeError::BufferTooSmall {
+            needed: 9,
+            available: 8
+        })
+    );
+    assert_eq!(buf, [0xff; 8]);
+}
+
+#[test]
+fn test_vec() {
+    let mut v = vec![1u8];
+    peek_poke::try_poke_into_vec(&2u16, &mut v).unwrap();
+    assert_eq!(v.len(), 3);
+    assert_eq!(
+        peek_poke::try_poke_extend_vec([3u8, 4, 5].iter(), &mut v),
+        Ok(3)
+    );
+    assert_eq!(v.len(), 6);
+    assert_eq!(&v[3..], &[3, 4, 5]);
+    peek_poke::try_ensure_red_zone::<u32>(&mut v).unwrap();
+    assert_eq!(&v[6..], &[0; 4]);
+}
+
+#[test]
+fn test_vec_alloc_error() {
+    let mut v = vec![1u8, 2, 3];
+    let res = peek_poke::try_poke_extend_vec((0..usize::MAX / 4).map(|_| 0u64), &mut v);
+    assert!(matches!(res, Err(PokeError::AllocError(_))));
+    assert_eq!(v, [1, 2, 3]);
+}
@@ -0,0 +1,215 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+mod common;
+
+use common::poke;
+use peek_poke::{PeekError, PeekPoke, PeekReader, Poke, TryPeek, Varint, ZigZag};
+use std::fmt::Debug;
+
+fn the_same<T: Debug + PartialEq + TryPeek>(value: T) {
+    let bytes = poke(&value);
+    let mut reader = PeekReader::new(&bytes);
+    assert_eq!(reader.read::<T>().as_ref(), Ok(&value));
+    assert!(reader.is_empty());
+
+    let mut padded = bytes.clone();
+    peek_poke::ensure_red_zone::<T>(&mut padded);
+    let (peeked, end) = unsafe { peek_poke::peek_from_uninit::<T>(padded.as_ptr()) };
+    assert_eq!(end as usize - padded.as_ptr() as usize, bytes.len());
+    assert_eq!(peeked, value);
+}
+
+fn try_read<T: Debug + TryPeek>(bytes: &[u8]) -> Result<T, PeekError> {
+    PeekReader::new(bytes).read::<T>()
+}
+
+#[test]
+fn test_max_size() {
+    assert_eq!(Varint::<u8>::MAX_SIZE, 2);
+    assert_eq!(Varint::<u16>::MAX_SIZE, 3);
+    assert_eq!(Varint::<u32>::MAX_SIZE, 5);
+    assert_eq!(Varint::<u64>::MAX_SIZE, 10);
+    assert_eq!(ZigZag::<i8>::MAX_SIZE, 2);
+    assert_eq!(ZigZag::<i32>::MAX_SIZE, 5);
+    assert_eq!(ZigZag::<i64>::MAX_SIZE, 10);
+}
+
+#[test]
+fn test_varint_encoding() {
+    assert_eq!(poke(&Varint(0u32)), [0]);
+    assert_eq!(poke(&Varint(127u32)), [0x7f]);
+    assert_eq!(poke(&Varint(128u32)), [0x80, 0x01]);
+    assert_eq!(poke(&Varint(300u16)), [0xac, 0x02]);
+    assert_eq!(poke(&Varint(u8::MAX)), [0xff, 0x01]);
+    assert_eq!(poke(&Varint(u32::MAX)), [0xff, 0xff, 0xff, 0xff, 0x0f]);
+    let max = poke(&Varint(u64::MAX));
+    assert_eq!(max.len(), 10);
+    assert_eq!(max[9], 0x01);
+}
+
+#[test]
+fn test_zigzag_encoding() {
+    assert_eq!(poke(&ZigZag(0i32)), [0]);
+    assert_eq!(poke(&ZigZag(-1i32)), [1]);
+    assert_eq!(poke(&ZigZag(1i32)), [2]);
+    assert_eq!(poke(&ZigZag(-2i32)), [3]);
+    assert_eq!(poke(&ZigZag(-64i32)), [0x7f]);
+    assert_eq!(poke(&ZigZag(64i32)), [0x80, 0x01]);
+    assert_eq!(poke(&ZigZag(i8::MIN)), [0xff, 0x01]);
+    assert_eq!(poke(&ZigZag(i64::MIN)).len(), 10);
+}
+
+#[test]
+fn test_round_trip() {
+    for &v in &[0u64, 1, 127, 128, 16383, 16384, u32::MAX as u64, u64::MAX] {
+        the_same(Varint(v));
+        the_same(Varint(v as usize));
+        the_same(Varint(v as u32));
+        the_same(Varint(v as u16));
+        the_same(Varint(v as u8));
+    }
+    for &v in &[
+        0i64,
+        1,
+        -1,
+        63,
+        -64,
+        64,
+        -65,
+        i32::MIN as i64,
+        i64::MIN,
+        i64::MAX,
+    ] {
+        the_same(ZigZag(v));
+        the_same(ZigZag(v as isize));
+        the_same(ZigZag(v as i32));
+        the_same(ZigZag(v as i16));
+        the_same(ZigZag(v as i8));
+    }
+}
+
+#[test]
+fn test_truncated() {
+    assert_eq!(try_read::<Varint<u32>>(&[]), Err(PeekError::UnexpectedEnd));
+    assert_eq!(
+        try_read::<Varint<u32>>(&[0x80, 0x80]),
+        Err(PeekError::UnexpectedEnd)
+    );
+    assert_eq!(
+        try_read::<ZigZag<i64>>(&[0xff]),
+        Err(PeekError::UnexpectedEnd)
+    );
+}
+
+#[test]
+fn test_overlong() {
+    let invalid = Err(PeekError::InvalidValue {
+        type_name: "Varint<u32>",
+    });
+    // Zero and one with a redundant continuation byte.
+    assert_eq!(try_read::<Varint<u32>>(&[0x80, 0x00]), invalid);
+    assert_eq!(try_read::<Varint<u32>>(&[0x81, 0x80, 0x00]), invalid);
+    // More bytes than the type can need.
+    assert_eq!(
+        try_read::<Varint<u32>>(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]),
+        invalid
+    );
+}
+
+#[test]
+fn test_overflow() {
+    assert_eq!(
+        try_read::<Varint<u8>>(&[0xff, 0x02]),
+        Err(PeekError::InvalidValue {
+            type_name: "Varint<u8>"
+        })
+    );
+    assert_eq!(
+        try_read::<Varint<u32>>(&[0xff, 0xff, 0xff, 0xff, 0x1f]),
+        Err(PeekError::InvalidValue {
+            type_name: "Varint<u32>"
+        })
+    );
+    let mut bytes = [0xff; 10];
+    bytes[9] = 0x02;
+    assert_eq!(
+        try_read::<Varint<u64>>(&bytes),
+        Err(PeekError::InvalidValue {
+            type_name: "Varint<u64>"
+        })
+    );
+    assert_eq!(
+        try_read::<ZigZag<i16>>(&[0xff, 0xff, 0x04]),
+        Err(PeekError::InvalidValue {
+            type_name: "Varint<u16>"
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "memory corruption detected while parsing Varint<u8>")]
+fn test_peek_invalid_panics() {
+    let mut bytes = vec![0xff, 0x02];
+    peek_poke::ensure_red_zone::<Varint<u8>>(&mut bytes);
+    unsafe {
+        peek_poke::peek_from_uninit::<Varint<u8>>(bytes.as_ptr());
+    }
+}
+
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+struct DisplayItem {
+    #[peek_poke(varint)]
+    id: u64,
+    #[peek_poke(zigzag)]
+    offset: i32,
+    flags: u8,
+}
+
+#[derive(Debug, Default, PartialEq, PeekPoke)]
+enum ClipId {
+    #[default]
+    Root,
+    Clip(#[peek_poke(varint)] usize, u32),
+    Offset {
+        #[peek_poke(zigzag)]
+        x: i16,
+        #[peek_poke(zigzag)]
+        y: i16,
+    },
+}
+
+#[test]
+fn test_derive_field_attrs() {
+    assert_eq!(DisplayItem::MAX_SIZE, 10 + 5 + 1);
+    let item = DisplayItem {
+        id: 300,
+        offset: -2,
+        flags: 7,
+    };
+    assert_eq!(poke(&item), [0xac, 0x02, 0x03, 0x07]);
+    the_same(item);
+    the_same(DisplayItem {
+        id: u64::MAX,
+        offset: i32::MIN,
+        flags: 0,
+    });
+
+    assert_eq!(
+        ClipId::MAX_SIZE,
+        1 + Varint::<usize>::MAX_SIZE + u32::MAX_SIZE
+    );
+    let clip = ClipId::Clip(5, 0x0102_0304);
+    assert_eq!(poke(&clip)[..2], [1, 5]);
+    the_same(clip);
+    the_same(ClipId::Offset { x: -1, y: 200 });
+    assert_eq!(poke(&ClipId::Offset { x: -1, y: 1 }), [2, 1, 2]);
+    the_same(ClipId::Root);
+}
@@ -0,0 +1,189 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![allow(dead_code, clippy::assertions_on_constants)]
+
+use peek_poke::{Le, PeekError, PeekPoke, PeekReader, Poke};
+use std::{marker::PhantomData, num::NonZeroU32};
+
+const NATIVE_ORDER: bool = !cfg!(feature = "little_endian") || cfg!(target_endian = "little");
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Wrap<T>(T);
+
+#[derive(Default, PeekPoke)]
+struct NotReprC {
+    x: f32,
+    y: f32,
+}
+
+#[repr(C)]
+#[derive(Default, PeekPoke)]
+struct Padded {
+    a: u8,
+    b: u32,
+}
+
+#[repr(C)]
+#[derive(Default, PeekPoke)]
+struct WithBool {
+    a: u32,
+    b: bool,
+}
+
+#[repr(C)]
+#[derive(Default, PeekPoke)]
+struct WithVarint {
+    #[peek_poke(varint)]
+    a: u32,
+}
+
+#[test]
+fn test_primitives() {
+    assert_eq!(u8::VERBATIM, NATIVE_ORDER);
+    assert_eq!(u32::VERBATIM, NATIVE_ORDER);
+    assert_eq!(f64::VERBATIM, NATIVE_ORDER);
+    assert_eq!(<[i16; 3]>::VERBATIM, NATIVE_ORDER);
+    assert_eq!(
+        usize::VERBATIM,
+        NATIVE_ORDER && (!cfg!(feature = "fixed_usize") || cfg!(target_pointer_width = "64"))
+    );
+    assert_eq!(Le::<u32>::VERBATIM, cfg!(target_endian = "little"));
+    assert!(PhantomData::<u32>::VERBATIM);
+    assert!(!bool::VERBATIM);
+    assert!(!char::VERBATIM);
+    assert!(!NonZeroU32::VERBATIM);
+    assert!(!Option::<u32>::VERBATIM);
+    assert!(!<(u32, u32)>::VERBATIM);
+}
+
+#[test]
+fn test_derive() {
+    assert_eq!(Point::VERBATIM, NATIVE_ORDER);
+    assert_eq!(Wrap::<u64>::VERBATIM, NATIVE_ORDER);
+    assert_eq!(<[Point; 2]>::VERBATIM, NATIVE_ORDER);
+    assert!(!Wrap::<bool>::VERBATIM);
+    assert!(!NotReprC::VERBATIM);
+    assert!(!Padded::VERBATIM);
+    assert!(!WithBool::VERBATIM);
+    assert!(!WithVarint::VERBATIM);
+}
+
+fn points() -> Vec<Point> {
+    (0..10)
+        .map(|i| Point {
+            x: i as f32,
+            y: -(i as f32),
+        })
+        .collect()
+}
+
+#[test]
+fn test_slice_round_trip() {
+    let points = points();
+    let mut bytes = Vec::new();
+    peek_poke::poke_slice_into_vec(&points, &mut bytes);
+    let mut expected = Vec::new();
+    peek_poke::poke_extend_vec(points.iter(), &mut expected);
+    assert_eq!(bytes, expected);
+
+    let mut reader = PeekReader::new(&bytes);
+    let mut read = vec![Point::default(); points.len()];
+    reader.read_slice_into(&mut read).unwrap();
+    assert_eq!(read, points);
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn test_slice_truncated() {
+    let mut bytes = Vec::new();
+    peek_poke::poke_slice_into_vec(&points(), &mut bytes);
+    bytes.pop();
+    let mut reader = PeekReader::new(&bytes);
+    let mut read = vec![Point::default(); 10];
+    assert_eq!(
+        reader.read_slice_into(&mut read),
+        Err(PeekError::UnexpectedEnd)
+    );
+    assert_eq!(reader.position(), 0);
+    reader.read_slice_into(&mut read[..9]).unwrap();
+    assert_eq!(read[..9], points()[..9]);
+}
+
+#[test]
+fn test_slice_not_verbatim() {
+    let values = [Some(1u32), None, Some(3)];
+    let mut bytes = Vec::new();
+    peek_poke::poke_slice_into_vec(&values, &mut bytes);
+    assert_eq!(bytes.len(), 5 + 1 + 5);
+    let mut read = [None; 3];
+    PeekReader::new(&bytes).read_slice_into(&mut read).unwrap();
+    assert_eq!(read, values);
+}
+
+#[test]
+fn test_array_round_trip() {
+    let array = [Wrap([1u16, 2]), Wrap([3, 4]), Wrap([5, 6])];
+    let mut bytes = Vec::new();
+    peek_poke::poke_into_vec(&array, &mut bytes);
+    assert_eq!(bytes.len(), 12);
+    assert_eq!(PeekReader::new(&bytes).read(), Ok(array));
+    assert_eq!(
+        PeekReader::new(&bytes[..11]).read::<[Wrap<[u16; 2]>; 3]>(),
+        Err(PeekError::UnexpectedEnd)
+    );
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct PipelineId(u32, u32);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct ClipChainId(u64, PipelineId);
+
+#[test]
+fn test_derive_whole_struct() {
+    let id = ClipChainId(1, PipelineId(2, 3));
+    assert_eq!(ClipChainId::VERBATIM, NATIVE_ORDER);
+    let mut bytes = Vec::new();
+    peek_poke::poke_into_vec(&id, &mut bytes);
+    let mut expected = Vec::new();
+    peek_poke::poke_into_vec(&(1u64, 2u32, 3u32), &mut expected);
+    assert_eq!(bytes, expected);
+
+    assert_eq!(PeekReader::new(&bytes).read(), Ok(id));
+    assert_eq!(
+        unsafe { peek_poke::peek_from_default::<ClipChainId>(bytes.as_ptr()).0 },
+        id
+    );
+    assert_eq!(
+        PeekReader::new(&bytes[..15]).read::<ClipChainId>(),
+        Err(PeekError::UnexpectedEnd)
+    );
+}
+
+#[test]
+fn test_derive_not_verbatim() {
+    let wrap = Wrap(Some(5u32));
+    assert!(!Wrap::<Option<u32>>::VERBATIM);
+    let mut bytes = Vec::new();
+    peek_poke::poke_into_vec(&wrap, &mut bytes);
+    assert_eq!(bytes.len(), 5);
+    assert_eq!(PeekReader::new(&bytes).read(), Ok(wrap));
+}
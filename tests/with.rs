@@ -0,0 +1,122 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+mod common;
+
+use common::poke;
+use peek_poke::{PeekError, PeekPoke, PeekReader, Poke};
+use std::{net::Ipv4Addr, time::Duration};
+
+/// A `Duration` as whole milliseconds.
+mod millis {
+    use peek_poke::Poke;
+    use std::{mem::MaybeUninit, time::Duration};
+
+    pub const MAX_SIZE: usize = u64::MAX_SIZE;
+
+    pub unsafe fn poke_into(value: &Duration, bytes: *mut u8) -> *mut u8 {
+        (value.as_millis() as u64).poke_into(bytes)
+    }
+
+    pub unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Duration>) -> *const u8 {
+        let (ms, bytes) = peek_poke::peek_from_uninit::<u64>(bytes);
+        output.write(Duration::from_millis(ms));
+        bytes
+    }
+}
+
+/// An `Ipv4Addr` as its octets.
+mod octets {
+    use peek_poke::Poke;
+    use std::{mem::MaybeUninit, net::Ipv4Addr};
+
+    pub const MAX_SIZE: usize = <[u8; 4]>::MAX_SIZE;
+
+    pub unsafe fn poke_into(value: &Ipv4Addr, bytes: *mut u8) -> *mut u8 {
+        value.octets().poke_into(bytes)
+    }
+
+    pub unsafe fn peek_from(bytes: *const u8, output: &mut MaybeUninit<Ipv4Addr>) -> *const u8 {
+        let (octets, bytes) = peek_poke::peek_from_uninit::<[u8; 4]>(bytes);
+        output.write(Ipv4Addr::from(octets));
+        bytes
+    }
+}
+
+#[derive(Debug, PartialEq, PeekPoke)]
+struct Request {
+    id: u16,
+    #[peek_poke(with = "octets")]
+    addr: Ipv4Addr,
+    #[peek_poke(with = "millis")]
+    timeout: Duration,
+}
+
+#[derive(Debug, PartialEq, PeekPoke)]
+enum Event {
+    Connect(#[peek_poke(with = "octets")] Ipv4Addr),
+    Wait {
+        #[peek_poke(with = "millis")]
+        timeout: Duration,
+        retry: bool,
+    },
+}
+
+#[test]
+fn test_struct() {
+    assert_eq!(Request::MAX_SIZE, 2 + 4 + 8);
+    let request = Request {
+        id: 3,
+        addr: Ipv4Addr::new(10, 0, 0, 1),
+        timeout: Duration::from_millis(1500),
+    };
+    let bytes = poke(&request);
+    assert_eq!(bytes, poke(&(3u16, [10u8, 0, 0, 1], 1500u64)));
+
+    let mut padded = bytes.clone();
+    peek_poke::ensure_red_zone::<Request>(&mut padded);
+    let (peeked, end) = unsafe { peek_poke::peek_from_uninit::<Request>(padded.as_ptr()) };
+    assert_eq!(peeked, request);
+    assert_eq!(end as usize - padded.as_ptr() as usize, bytes.len());
+
+    let mut reader = PeekReader::new(&bytes);
+    assert_eq!(reader.read::<Request>(), Ok(request));
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn test_enum() {
+    assert_eq!(Event::MAX_SIZE, 1 + 8 + 1);
+    for event in [
+        Event::Connect(Ipv4Addr::LOCALHOST),
+        Event::Wait {
+            timeout: Duration::from_secs(2),
+            retry: true,
+        },
+    ] {
+        let bytes = poke(&event);
+        let mut reader = PeekReader::new(&bytes);
+        assert_eq!(reader.read::<Event>(), Ok(event));
+        assert!(reader.is_empty());
+    }
+}
+
+#[test]
+fn test_truncated() {
+    let bytes = poke(&Event::Wait {
+        timeout: Duration::from_millis(5),
+        retry: false,
+    });
+    for len in 0..bytes.len() {
+        let mut reader = PeekReader::new(&bytes[..len]);
+        assert_eq!(reader.read::<Event>(), Err(PeekError::UnexpectedEnd));
+        assert_eq!(reader.position(), 0);
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2019 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use peek_poke::{PeekPoke, PeekReader, PokeError, PokeWriter};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PeekPoke)]
+struct Bar {
+    a: u32,
+    b: Option<u64>,
+}
+
+#[test]
+fn test_vec() {
+    let mut v = vec![0xffu8];
+    let mut writer = PokeWriter::from_vec(&mut v);
+    assert!(writer.is_empty());
+    writer.write(&Bar { a: 1, b: None }).unwrap();
+    assert_eq!(writer.extend([2u16, 3].iter()), Ok(2));
+    assert_eq!(writer.len(), 9);
+    assert_eq!(writer.finish().len(), 9);
+    assert_eq!(v.len(), 10);
+
+    let mut reader = PeekReader::new(&v[1..]);
+    assert_eq!(reader.read::<Bar>(), Ok(Bar { a: 1, b: None }));
+    assert_eq!(reader.read::<u16>(), Ok(2));
+    assert_eq!(reader.read::<u16>(), Ok(3));
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn test_slice() {
+    let mut buf = [0xffu8; 8];
+    let mut writer = PokeWriter::from_slice(&mut buf);
+    writer.write(&1u32).unwrap();
+    // `None` fits, even though `Bar::MAX_SIZE` is larger than what's left.
+    writer.write(&None::<u64>).unwrap();
+    assert_eq!(
+        writer.write(&Some(2u64)),
+        Err(PokeError::BufferTooSmall {
+            needed: 9,
+            available: 3
+        })
+    );
+    assert_eq!(writer.len(), 5);
+    assert_eq!(writer.finish(), &[1, 0, 0, 0, 0][..]);
+    assert_eq!(&buf[5..], &[0xff; 3]);
+}
+
+#[test]
+fn test_extend_restores_len() {
+    let mut buf = [0u8; 5];
+    let mut writer = PokeWriter::from_slice(&mut buf);
+    writer.write(&1u8).unwrap();
+    assert_eq!(
+        writer.extend([2u16, 3, 4].iter()),
+        Err(PokeError::BufferTooSmall {
+            needed: 2,
+            available: 0
+        })
+    );
+    assert_eq!(writer.len(), 1);
+    assert_eq!(writer.finish(), &[1][..]);
+}
+
+#[test]
+fn test_spare_capacity() {
+    let mut v = Vec::with_capacity(16);
+    v.push(0xffu8);
+    let written = {
+        let mut writer = PokeWriter::from_uninit(v.spare_capacity_mut());
+        writer.write(&Bar { a: 7, b: Some(8) }).unwrap();
+        writer.finish().len()
+    };
+    unsafe { v.set_len(1 + written) };
+
+    let mut reader = PeekReader::new(&v[1..]);
+    assert_eq!(reader.read::<Bar>(), Ok(Bar { a: 7, b: Some(8) }));
+    assert!(reader.is_empty());
+}